@@ -0,0 +1,116 @@
+use ::core::{ops::Add, time};
+
+/// A point in time that drives the timers of the send path.
+pub trait Timestamp: Copy + Ord {
+    /// The time elapsed since `earlier`, or zero if `earlier` is later than `self`.
+    fn saturating_elapsed(&self, earlier: Self) -> time::Duration;
+}
+
+#[cfg(feature = "std")]
+impl Timestamp for std::time::Instant {
+    fn saturating_elapsed(&self, earlier: Self) -> time::Duration {
+        self.saturating_duration_since(earlier)
+    }
+}
+
+/// A monotonic tick counter, for environments without `Instant` or driven by a logical clock.
+///
+/// A tick counts as one nanosecond, so a timeout of `Duration::from_nanos(n)` spans `n` ticks.
+impl Timestamp for u64 {
+    fn saturating_elapsed(&self, earlier: Self) -> time::Duration {
+        time::Duration::from_nanos(self.saturating_sub(earlier))
+    }
+}
+
+pub trait Clock {
+    type Timestamp: Timestamp;
+
+    fn now(&self) -> Self::Timestamp;
+}
+
+/// The monotonic clock of the OS
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    type Timestamp = std::time::Instant;
+
+    fn now(&self) -> Self::Timestamp {
+        std::time::Instant::now()
+    }
+}
+
+/// The time since an arbitrary epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct MockInstant(time::Duration);
+
+impl MockInstant {
+    #[must_use]
+    pub fn from_epoch(since_epoch: time::Duration) -> Self {
+        Self(since_epoch)
+    }
+
+    #[must_use]
+    pub fn since_epoch(&self) -> time::Duration {
+        self.0
+    }
+}
+
+impl Timestamp for MockInstant {
+    fn saturating_elapsed(&self, earlier: Self) -> time::Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl Add<time::Duration> for MockInstant {
+    type Output = Self;
+
+    fn add(self, rhs: time::Duration) -> Self::Output {
+        Self(self.0 + rhs)
+    }
+}
+
+/// A clock that only advances on command, for deterministic tests and simulations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockClock {
+    now: MockInstant,
+}
+
+impl MockClock {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&mut self, duration: time::Duration) {
+        self.now = self.now + duration;
+    }
+}
+
+impl Clock for MockClock {
+    type Timestamp = MockInstant;
+
+    fn now(&self) -> Self::Timestamp {
+        self.now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock() {
+        let mut clock = MockClock::new();
+        let start = clock.now();
+        clock.advance(time::Duration::from_millis(100));
+        let now = clock.now();
+        assert_eq!(
+            now.saturating_elapsed(start),
+            time::Duration::from_millis(100)
+        );
+        assert_eq!(start.saturating_elapsed(now), time::Duration::ZERO);
+    }
+}
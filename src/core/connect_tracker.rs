@@ -0,0 +1,131 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    time,
+};
+
+use super::{ConnectResult, Timestamp};
+use crate::frame::{ConnectSecure, ConnectionId};
+
+/// Reject replayed `ConnectSecure` frames.
+///
+/// A `(connection ID, nonce)` pair is remembered for `window` and at most `capacity` pairs are remembered, the oldest being forgotten first.
+pub struct ConnectTracker<T = time::Instant> {
+    window: time::Duration,
+    capacity: usize,
+    /// The pairs seen within the window
+    seen: HashSet<(ConnectionId, u64)>,
+    /// The same pairs from the oldest to the newest
+    recent: VecDeque<(T, (ConnectionId, u64))>,
+}
+
+impl<T> ConnectTracker<T>
+where
+    T: Timestamp,
+{
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[must_use]
+    pub fn new(window: time::Duration, capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            window,
+            capacity,
+            seen: HashSet::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// A pair seen within the window is a replay and must not reset any state.
+    #[must_use]
+    pub fn connect(&mut self, now: T, connect: &ConnectSecure) -> ConnectResult {
+        self.expire(now);
+
+        let pair = (connect.connection_id, connect.nonce);
+        if self.seen.contains(&pair) {
+            return ConnectResult::Duplicate;
+        }
+
+        // Forget the oldest pair
+        if self.recent.len() == self.capacity {
+            if let Some((_, oldest)) = self.recent.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        self.seen.insert(pair);
+        self.recent.push_back((now, pair));
+        ConnectResult::New
+    }
+
+    /// The number of pairs remembered
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.recent.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.recent.is_empty()
+    }
+
+    fn expire(&mut self, now: T) {
+        while let Some((seen_at, pair)) = self.recent.front() {
+            if now.saturating_elapsed(*seen_at) < self.window {
+                break;
+            }
+            self.seen.remove(pair);
+            self.recent.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay() {
+        let mut tracker = ConnectTracker::new(time::Duration::from_secs(10), 2);
+        let now = time::Instant::now();
+        let connect = ConnectSecure {
+            connection_id: 1.into(),
+            nonce: 42,
+        };
+        assert_eq!(tracker.connect(now, &connect), ConnectResult::New);
+        assert_eq!(tracker.connect(now, &connect), ConnectResult::Duplicate);
+
+        // A new attempt with a fresh nonce
+        let retry = ConnectSecure {
+            connection_id: 1.into(),
+            nonce: 43,
+        };
+        assert_eq!(tracker.connect(now, &retry), ConnectResult::New);
+        assert_eq!(tracker.len(), 2);
+
+        // Out of the window
+        let now = now + time::Duration::from_secs(10);
+        assert_eq!(tracker.connect(now, &connect), ConnectResult::New);
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut tracker = ConnectTracker::new(time::Duration::from_secs(10), 2);
+        let now = time::Instant::now();
+        let connects = (0..3)
+            .map(|nonce| ConnectSecure {
+                connection_id: 1.into(),
+                nonce,
+            })
+            .collect::<Vec<_>>();
+        for connect in &connects {
+            assert_eq!(tracker.connect(now, connect), ConnectResult::New);
+        }
+        assert_eq!(tracker.len(), 2);
+
+        // The oldest pair is forgotten
+        assert_eq!(tracker.connect(now, &connects[2]), ConnectResult::Duplicate);
+        assert_eq!(tracker.connect(now, &connects[0]), ConnectResult::New);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    os::fd::RawFd,
+    time,
+};
+
+use seq::Seq16;
+
+use super::{
+    sender::{
+        AckSpace, ReassignPayloadError, RetransmitPayloads, SendConfig, SendFrame, Sender,
+        Timestamp,
+    },
+    ReceiveQueue, ReceiveResult,
+};
+use crate::frame::{Frame, Payload, PayloadAck, Ping, PingAck};
+
+/// Tie a `Sender` and a `ReceiveQueue` together over a set of sockets.
+///
+/// Decoded frames go into `on_frame` and outgoing frames come out of `on_frame` and `poll_send`, each paired with the socket to write it to.
+pub struct Connection<T = time::Instant> {
+    sender: Sender<T>,
+    receive_queue: ReceiveQueue<T>,
+    fds: BTreeSet<RawFd>,
+    /// Data of the payloads sent but not yet acked
+    unacked: HashMap<Seq16, Vec<u8>>,
+    /// Data of the payloads received but not yet delivered
+    received: HashMap<Seq16, Vec<u8>>,
+    /// Payloads left without a socket, to resend once there is one
+    orphaned: Vec<Seq16>,
+}
+
+impl<T> Connection<T>
+where
+    T: Timestamp,
+{
+    /// # Panics
+    ///
+    /// Panics if `config` is invalid.
+    #[must_use]
+    pub fn new(config: SendConfig, recv_window: usize) -> Self {
+        Self {
+            sender: Sender::new(config),
+            receive_queue: ReceiveQueue::with_timestamp(recv_window, Seq16::new(0)),
+            fds: BTreeSet::new(),
+            unacked: HashMap::new(),
+            received: HashMap::new(),
+            orphaned: Vec::new(),
+        }
+    }
+
+    pub fn add_fd(&mut self, fd: RawFd) {
+        if self.fds.insert(fd) {
+            self.sender.add_fd(fd);
+        }
+    }
+
+    /// Return the payloads moved to the other sockets.
+    ///
+    /// If no socket is left, the payloads are resent by `poll_send` once a socket is added.
+    #[must_use]
+    pub fn remove_fd(&mut self, fd: RawFd) -> Vec<(RawFd, Frame)> {
+        if !self.fds.remove(&fd) {
+            return Vec::new();
+        }
+        let res = self.sender.remove_fd(fd);
+        self.retransmit(res)
+    }
+
+    #[must_use]
+    pub fn fds(&self) -> &BTreeSet<RawFd> {
+        &self.fds
+    }
+
+    #[must_use]
+    pub fn sender(&self) -> &Sender<T> {
+        &self.sender
+    }
+
+    /// Handle a frame received on `fd` and return the frames to send in response.
+    #[must_use]
+    pub fn on_frame(&mut self, now: T, fd: RawFd, frame: Frame) -> Vec<(RawFd, Frame)> {
+        if !self.fds.contains(&fd) {
+            return Vec::new();
+        }
+        match frame {
+            Frame::Payload(payload) => {
+                match self.receive_queue.receive(payload.seq) {
+                    ReceiveResult::AcceptDeliverable(_) => {
+                        self.received.insert(payload.seq, payload.data);
+                    }
+                    ReceiveResult::RejectTooOld | ReceiveResult::RejectDuplicate => {
+                        // The previous ack might have been lost
+                    }
+                    ReceiveResult::RejectBeyondWindow => return Vec::new(),
+                }
+                let ack = Frame::PayloadAck(PayloadAck { seq: payload.seq });
+                vec![(fd, ack)]
+            }
+            Frame::PayloadAck(PayloadAck { seq }) => {
+                self.ack_payload(now, fd, seq);
+                Vec::new()
+            }
+            Frame::PayloadAckEcn(payload_ack_ecn) => {
+                self.ack_payload(now, fd, payload_ack_ecn.seq);
+                Vec::new()
+            }
+            Frame::Ping(Ping { seq }) => {
+                vec![(fd, Frame::PingAck(PingAck { seq }))]
+            }
+            Frame::PingAck(PingAck { seq }) => {
+                self.sender.ack(now, fd, seq, AckSpace::Ping);
+                Vec::new()
+            }
+            Frame::Nack(nack) => {
+                let Some(retx) = self.sender.nack(fd, nack.seq) else {
+                    return Vec::new();
+                };
+                self.retransmit(Ok(retx))
+            }
+            Frame::WindowUpdate(window_update) => {
+                self.sender.set_peer_window(window_update.available.into());
+                Vec::new()
+            }
+            Frame::Ack(ack) => {
+                for seq in self.sender.apply_ack_frame(now, fd, &ack) {
+                    self.unacked.remove(&seq);
+                }
+                Vec::new()
+            }
+            Frame::Connect(_)
+            | Frame::ConnectV2(_)
+            | Frame::Hello(_)
+            | Frame::PayloadFragment(_)
+            | Frame::ConnectSecure(_)
+            | Frame::Private(_) => {
+                // Left to the caller
+                Vec::new()
+            }
+        }
+    }
+
+    /// Schedule the head of `data` and the due retransmissions.
+    ///
+    /// Also return the number of bytes taken from `data`; the caller should retry the rest later.
+    /// At most `u16::MAX` bytes are taken per call so every payload fits in a frame.
+    #[must_use]
+    pub fn poll_send(&mut self, now: T, data: &[u8]) -> (Vec<(RawFd, Frame)>, usize) {
+        // Retransmit timed out payloads
+        let unacked = &self.unacked;
+        let mut frames = self
+            .sender
+            .on_timeout_tick(now, |seq| unacked.get(&seq).cloned());
+
+        // Resend the orphaned payloads once there is a socket
+        if !self.orphaned.is_empty() && !self.fds.is_empty() {
+            let orphaned = std::mem::take(&mut self.orphaned);
+            let res = self.sender.reenqueue_orphaned(now, orphaned);
+            frames.extend(self.retransmit(res));
+        }
+
+        // Send new data
+        let data = &data[..usize::min(data.len(), u16::MAX as usize)];
+        let (send_frames, unscheduled) = self.sender.send_with_backpressure(now, data.len());
+        let mut offset = 0;
+        for frame in send_frames {
+            match frame {
+                SendFrame::Payload(frame) => {
+                    let chunk = data[offset..offset + frame.payload_size].to_vec();
+                    offset += frame.payload_size;
+                    self.unacked.insert(frame.seq, chunk.clone());
+                    let payload = Payload {
+                        seq: frame.seq,
+                        data: chunk,
+                        retransmit: false,
+                    };
+                    frames.push((frame.fd, Frame::Payload(payload)));
+                }
+                SendFrame::Ping(frame) => {
+                    frames.push((frame.fd, Frame::Ping(Ping { seq: frame.seq })));
+                }
+                SendFrame::Retransmit(frame) => {
+                    frames.extend(self.retransmit(Ok(vec![(frame.fd, frame.seq)])));
+                }
+            }
+        }
+
+        (frames, data.len() - unscheduled)
+    }
+
+    /// Pop the data of the next payload in order.
+    #[must_use]
+    pub fn recv(&mut self) -> Option<Vec<u8>> {
+        let seq = self.receive_queue.pop()?;
+        self.received.remove(&seq)
+    }
+
+    fn ack_payload(&mut self, now: T, fd: RawFd, seq: Seq16) {
+        self.sender.ack(now, fd, seq, AckSpace::Payload);
+        self.unacked.remove(&seq);
+    }
+
+    /// Turn reassigned payloads into frames and keep the ones orphaned by `Sender::remove_fd`.
+    fn retransmit(
+        &mut self,
+        res: Result<RetransmitPayloads, ReassignPayloadError>,
+    ) -> Vec<(RawFd, Frame)> {
+        let retx = match res {
+            Ok(retx) => retx,
+            Err(ReassignPayloadError::NoSocketsLeft { payloads }) => {
+                self.orphaned.extend(payloads);
+                return Vec::new();
+            }
+        };
+        retx.into_iter()
+            .filter_map(|(fd, seq)| {
+                let data = self.unacked.get(&seq)?.clone();
+                let payload = Payload {
+                    seq,
+                    data,
+                    retransmit: true,
+                };
+                Some((fd, Frame::Payload(payload)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    #[test]
+    fn end_to_end() {
+        let config = SendConfig {
+            payload_queue_size: 64,
+            default_rto: time::Duration::from_millis(100),
+            rng_seed: Some(0),
+            ..SendConfig::default()
+        };
+        let mut a = Connection::new(config, 64);
+        let mut b = Connection::new(config, 64);
+        for fd in [1, 2] {
+            a.add_fd(fd);
+            b.add_fd(fd);
+        }
+
+        // Each socket connects `a` and `b` through an in-memory channel
+        let mut a_to_b = VecDeque::new();
+        let mut b_to_a = VecDeque::new();
+        let encode = |(fd, frame): (RawFd, Frame)| (fd, Vec::<u8>::from(&frame));
+        let decode = |buf: &[u8]| {
+            let mut reader = std::io::BufReader::new(buf);
+            Frame::decode(&mut reader).unwrap()
+        };
+
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut sent = 0;
+        let mut received = Vec::new();
+        let mut now = time::Instant::now();
+        for _ in 0..100 {
+            let (frames, taken) = a.poll_send(now, &data[sent..usize::min(sent + 100, data.len())]);
+            sent += taken;
+            a_to_b.extend(frames.into_iter().map(encode));
+
+            now += time::Duration::from_millis(1);
+            while let Some((fd, buf)) = a_to_b.pop_front() {
+                let frames = b.on_frame(now, fd, decode(&buf));
+                b_to_a.extend(frames.into_iter().map(encode));
+            }
+            while let Some(data) = b.recv() {
+                received.extend(data);
+            }
+
+            now += time::Duration::from_millis(1);
+            while let Some((fd, buf)) = b_to_a.pop_front() {
+                let frames = a.on_frame(now, fd, decode(&buf));
+                a_to_b.extend(frames.into_iter().map(encode));
+            }
+
+            if received.len() == data.len() {
+                break;
+            }
+        }
+        assert_eq!(received, data);
+        assert!(a.unacked.is_empty());
+        assert_eq!(a.sender().in_flight_bytes(), 0);
+    }
+
+    #[test]
+    fn retransmit_dropped_payloads() {
+        let config = SendConfig {
+            payload_queue_size: 64,
+            default_rto: time::Duration::from_millis(100),
+            rng_seed: Some(0),
+            ..SendConfig::default()
+        };
+        let mut a = Connection::new(config, 64);
+        let mut b = Connection::new(config, 64);
+        for fd in [1, 2] {
+            a.add_fd(fd);
+            b.add_fd(fd);
+        }
+
+        let data = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let mut received = Vec::new();
+        let mut now = time::Instant::now();
+
+        // Earn RTT samples on both sockets
+        let (frames, taken) = a.poll_send(now, &data[..100]);
+        assert_eq!(taken, 100);
+        now += time::Duration::from_millis(1);
+        let acks = exchange(&mut b, now, frames);
+        now += time::Duration::from_millis(1);
+        assert!(exchange(&mut a, now, acks).is_empty());
+        while let Some(data) = b.recv() {
+            received.extend(data);
+        }
+
+        // Drop a round
+        let (frames, taken) = a.poll_send(now, &data[100..]);
+        assert_eq!(taken, 100);
+        let dropped = frames
+            .iter()
+            .filter(|(_, frame)| matches!(frame, Frame::Payload(_)))
+            .count();
+        assert_ne!(dropped, 0);
+
+        // Drop the first retransmission as well
+        now += time::Duration::from_millis(60);
+        let (frames, _) = a.poll_send(now, &[]);
+        let retransmitted = frames
+            .iter()
+            .filter(|(_, frame)| matches!(frame, Frame::Payload(_)))
+            .count();
+        assert_eq!(retransmitted, dropped);
+
+        // Poll well past the RTO
+        for _ in 0..10 {
+            now += time::Duration::from_millis(60);
+            let (frames, _) = a.poll_send(now, &[]);
+            now += time::Duration::from_millis(1);
+            let acks = exchange(&mut b, now, frames);
+            now += time::Duration::from_millis(1);
+            assert!(exchange(&mut a, now, acks).is_empty());
+            while let Some(data) = b.recv() {
+                received.extend(data);
+            }
+        }
+        assert_eq!(received, data);
+        assert!(a.unacked.is_empty());
+        assert_eq!(a.sender().in_flight_bytes(), 0);
+
+        // Each dropped payload timed out once per loss
+        assert_eq!(a.sender().metrics().rtos, 2 * dropped);
+    }
+
+    /// Carry `frames` to `to` through their wire encoding and return the frames sent in response.
+    fn exchange(
+        to: &mut Connection,
+        now: time::Instant,
+        frames: Vec<(RawFd, Frame)>,
+    ) -> Vec<(RawFd, Frame)> {
+        let mut responses = Vec::new();
+        for (fd, frame) in frames {
+            let buf = Vec::<u8>::from(&frame);
+            let mut reader = std::io::BufReader::new(&buf[..]);
+            let frame = Frame::decode(&mut reader).unwrap();
+            responses.extend(to.on_frame(now, fd, frame));
+        }
+        responses
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use crate::frame::{Connect, ConnectionId};
+
+pub struct ConnectionTable {
+    /// The connection IDs that have been seen
+    connections: HashSet<ConnectionId>,
+}
+
+impl ConnectionTable {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            connections: HashSet::new(),
+        }
+    }
+
+    /// A repeated `Connect` with a seen connection ID is a retransmission and must not create new state.
+    #[must_use]
+    pub fn connect(&mut self, connect: &Connect) -> ConnectResult {
+        match self.connections.insert(connect.connection_id) {
+            true => ConnectResult::New,
+            false => ConnectResult::Duplicate,
+        }
+    }
+
+    pub fn remove(&mut self, connection_id: ConnectionId) {
+        self.connections.remove(&connection_id);
+    }
+
+    #[must_use]
+    pub fn contains(&self, connection_id: ConnectionId) -> bool {
+        self.connections.contains(&connection_id)
+    }
+}
+
+impl Default for ConnectionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ConnectResult {
+    New,
+    Duplicate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_connect() {
+        let mut table = ConnectionTable::new();
+        let connect = Connect {
+            connection_id: 1.into(),
+        };
+        assert_eq!(table.connect(&connect), ConnectResult::New);
+        assert_eq!(table.connect(&connect), ConnectResult::Duplicate);
+        assert!(table.contains(1.into()));
+
+        let other = Connect {
+            connection_id: 2.into(),
+        };
+        assert_eq!(table.connect(&other), ConnectResult::New);
+
+        table.remove(1.into());
+        assert!(!table.contains(1.into()));
+        assert_eq!(table.connect(&connect), ConnectResult::New);
+    }
+}
@@ -1,4 +1,25 @@
+mod clock;
+#[cfg(feature = "std")]
+mod connect_tracker;
+#[cfg(feature = "std")]
+mod connection;
+#[cfg(feature = "std")]
+mod connection_table;
 mod receive_queue;
+mod scheduler;
+mod send_queue;
+#[cfg(feature = "std")]
 pub mod sender;
+mod seq_range;
 
+pub use clock::*;
+#[cfg(feature = "std")]
+pub use connect_tracker::*;
+#[cfg(feature = "std")]
+pub use connection::*;
+#[cfg(feature = "std")]
+pub use connection_table::*;
 pub use receive_queue::*;
+pub use scheduler::*;
+pub use send_queue::*;
+pub use seq_range::*;
@@ -1,40 +1,114 @@
-use std::collections::BTreeSet;
+use ::core::time;
+use alloc::{collections::BTreeSet, vec::Vec};
 
 use seq::Seq16;
 
-pub struct ReceiveQueue {
+use super::{clamp_window, seq_range, signed_dist, SeqOrder, Timestamp};
+
+/// `T` times the gaps for `expire_gaps`; it defaults to `Instant` with `std` and has to be named without.
+pub struct ReceiveQueue<
+    #[cfg(feature = "std")] T = std::time::Instant,
+    #[cfg(not(feature = "std"))] T,
+> {
     /// The queue of received packets
     queue: BTreeSet<Seq16>,
-    /// The maximum number of packets that can be stored in the queue
+    /// The maximum number of packets that can be stored in the queue, at most `MAX_WINDOW`
     capacity: usize,
     /// The first sequence of the receive window
     shadow_first: Seq16,
+    /// Sequences given up on by `pop_any` or `expire_gaps`
+    skipped: Vec<Seq16>,
+    /// The most packets buffered at once since creation or `reset_watermark`
+    high_watermark: usize,
+    /// The missing head and when `expire_gaps` first saw it missing
+    head_gap: Option<(Seq16, T)>,
 }
 
+#[cfg(feature = "std")]
 impl ReceiveQueue {
     #[must_use]
     pub fn new(capacity: usize) -> Self {
+        Self::with_start(capacity, Seq16::new(0))
+    }
+
+    /// `capacity` saturates at `MAX_WINDOW` so the window stays unambiguous.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `capacity` exceeds `MAX_WINDOW`.
+    #[must_use]
+    pub fn with_start(capacity: usize, shadow_first: Seq16) -> Self {
+        Self::with_timestamp(capacity, shadow_first)
+    }
+}
+
+impl<T> ReceiveQueue<T>
+where
+    T: Timestamp,
+{
+    /// Same as `with_start` but for any timestamp `T`, e.g. `u64` ticks without `std`.
+    #[must_use]
+    pub fn with_timestamp(capacity: usize, shadow_first: Seq16) -> Self {
         Self {
             queue: BTreeSet::new(),
-            capacity,
-            shadow_first: Seq16::new(0),
+            capacity: clamp_window(capacity),
+            shadow_first,
+            skipped: Vec::new(),
+            high_watermark: 0,
+            head_gap: None,
         }
     }
 
+    /// Drop all received packets and realign the receive window to `shadow_first`.
+    ///
+    /// The skipped sequences are forgotten as well.
+    pub fn reset(&mut self, shadow_first: Seq16) {
+        self.queue.clear();
+        self.skipped.clear();
+        self.shadow_first = shadow_first;
+        self.head_gap = None;
+    }
+
     #[must_use]
     pub fn receive(&mut self, seq: Seq16) -> ReceiveResult {
         // Reject out of bounds packets
-        if seq < self.shadow_first {
-            return ReceiveResult::Reject;
+        if seq.is_before(self.shadow_first) {
+            return ReceiveResult::RejectTooOld;
         }
-        if Seq16::dist(&self.shadow_first, &seq) as usize >= self.capacity {
-            return ReceiveResult::Reject;
+        if signed_dist(self.shadow_first, seq) as usize >= self.capacity {
+            return ReceiveResult::RejectBeyondWindow;
+        }
+
+        // Reject packets that are already buffered
+        if self.queue.contains(&seq) {
+            return ReceiveResult::RejectDuplicate;
         }
 
         // Insert the new packet
         self.queue.insert(seq);
+        self.high_watermark = usize::max(self.high_watermark, self.queue.len());
+
+        // Count the packets this one makes deliverable
+        if seq != self.shadow_first {
+            return ReceiveResult::AcceptDeliverable(0);
+        }
+        let mut deliverable = 0;
+        let mut next = seq;
+        while self.queue.contains(&next) {
+            deliverable += 1;
+            next = next.add(1);
+        }
+        ReceiveResult::AcceptDeliverable(deliverable)
+    }
 
-        return ReceiveResult::Accept;
+    /// Receive every sequence in the inclusive range `[start, end]`.
+    ///
+    /// Each sequence is checked against the receive window on its own, so a range straddling the window boundary is partially accepted.
+    #[must_use]
+    pub fn receive_range(&mut self, start: Seq16, end: Seq16) -> Vec<(Seq16, ReceiveResult)> {
+        seq_range(start, end)
+            .map(|seq| (seq, self.receive(seq)))
+            .collect()
     }
 
     #[must_use]
@@ -50,32 +124,154 @@ impl ReceiveQueue {
         first
     }
 
+    /// Pop the lowest buffered sequence even if there is a gap before it.
+    ///
+    /// The window slides past the gap and the sequences in it are declared lost; see `skipped`.
+    /// Unlike `pop`, this does not guarantee in-order delivery, which suits latency-sensitive data like media.
+    #[must_use]
+    pub fn pop_any(&mut self) -> Option<Seq16> {
+        let first = self.queue.pop_first()?;
+
+        // Give up on the gap
+        let mut seq = self.shadow_first;
+        while seq != first {
+            self.skipped.push(seq);
+            seq = seq.add(1);
+        }
+
+        self.shadow_first = first.add(1);
+        Some(first)
+    }
+
+    /// Give up on the missing head once it has been missing for `gap_timeout`.
+    ///
+    /// The window slides to the lowest buffered sequence so `pop` can deliver again, and the sequences in between are returned as lost; see `skipped`.
+    /// The head is timed from the first call that finds it missing, so call this regularly.
+    #[must_use]
+    pub fn expire_gaps(&mut self, now: T, gap_timeout: time::Duration) -> Vec<Seq16> {
+        // No gap without a packet after it
+        let Some(&first) = self.queue.first() else {
+            self.head_gap = None;
+            return Vec::new();
+        };
+        if first == self.shadow_first {
+            self.head_gap = None;
+            return Vec::new();
+        }
+
+        // Start timing a new head gap
+        let since = match self.head_gap {
+            Some((head, since)) if head == self.shadow_first => since,
+            _ => {
+                self.head_gap = Some((self.shadow_first, now));
+                now
+            }
+        };
+        if now.saturating_elapsed(since) < gap_timeout {
+            return Vec::new();
+        }
+
+        // Give up on the gap
+        let mut lost = Vec::new();
+        let mut seq = self.shadow_first;
+        while seq != first {
+            lost.push(seq);
+            seq = seq.add(1);
+        }
+        self.skipped.extend_from_slice(&lost);
+        self.shadow_first = first;
+        self.head_gap = None;
+        lost
+    }
+
+    /// Sequences declared lost by `pop_any` or `expire_gaps`, in ascending order
+    #[must_use]
+    pub fn skipped(&self) -> &[Seq16] {
+        &self.skipped
+    }
+
+    /// The number of packets received but not yet popped
+    #[must_use]
+    pub fn buffered_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// The peak of `buffered_count` since creation or `reset_watermark`, which helps tune the capacity.
+    #[must_use]
+    pub fn high_watermark(&self) -> usize {
+        self.high_watermark
+    }
+
+    /// Start tracking the peak from the current `buffered_count`.
+    pub fn reset_watermark(&mut self) {
+        self.high_watermark = self.queue.len();
+    }
+
+    /// Same as `with_start` on `capacity`.
     pub fn set_capacity(&mut self, capacity: usize) {
-        self.capacity = capacity;
+        self.capacity = clamp_window(capacity);
+    }
+
+    /// The room left to advertise in a `WindowUpdate` is `capacity - buffered_count`.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return `true` if `seq` is received but not yet popped.
+    #[must_use]
+    pub fn contains(&self, seq: Seq16) -> bool {
+        self.queue.contains(&seq)
+    }
+
+    /// The receive window as `(first, end)`, where `end` is exclusive
+    #[must_use]
+    pub fn window(&self) -> (Seq16, Seq16) {
+        (
+            self.shadow_first,
+            self.shadow_first.add(self.capacity as u16),
+        )
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum ReceiveResult {
-    Reject,
-    Accept,
+    /// The packet precedes the receive window and has already been delivered
+    RejectTooOld,
+    /// The packet is already buffered
+    RejectDuplicate,
+    /// The packet exceeds the receive window
+    RejectBeyondWindow,
+    /// The number of contiguous packets from the head that `pop` can now deliver, which is zero unless the packet filled the head
+    AcceptDeliverable(usize),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::{MockInstant, MAX_WINDOW};
 
     #[test]
     fn ok() {
         let mut queue = ReceiveQueue::new(2);
-        assert_eq!(queue.receive(Seq16::new(1)), ReceiveResult::Accept);
-        assert_eq!(queue.receive(Seq16::new(2)), ReceiveResult::Reject);
+        assert_eq!(
+            queue.receive(Seq16::new(1)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+        assert_eq!(queue.receive(Seq16::new(1)), ReceiveResult::RejectDuplicate);
+        assert_eq!(
+            queue.receive(Seq16::new(2)),
+            ReceiveResult::RejectBeyondWindow
+        );
         let first = queue.pop();
         assert_eq!(first, None);
-        assert_eq!(queue.receive(Seq16::new(0)), ReceiveResult::Accept);
+        assert_eq!(
+            queue.receive(Seq16::new(0)),
+            ReceiveResult::AcceptDeliverable(2)
+        );
         let first = queue.pop();
         assert_eq!(first, Some(Seq16::new(0)));
-        assert_eq!(queue.receive(Seq16::new(0)), ReceiveResult::Reject);
+        assert_eq!(queue.receive(Seq16::new(0)), ReceiveResult::RejectTooOld);
         let first = queue.pop();
         assert_eq!(first, Some(Seq16::new(1)));
         let first = queue.pop();
@@ -85,10 +281,21 @@ mod tests {
     #[test]
     fn reset_capacity() {
         let mut queue = ReceiveQueue::new(2);
-        assert_eq!(queue.receive(Seq16::new(0)), ReceiveResult::Accept);
-        assert_eq!(queue.receive(Seq16::new(1)), ReceiveResult::Accept);
+        assert_eq!(queue.capacity(), 2);
+        assert_eq!(
+            queue.receive(Seq16::new(0)),
+            ReceiveResult::AcceptDeliverable(1)
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(1)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
         queue.set_capacity(1);
-        assert_eq!(queue.receive(Seq16::new(1)), ReceiveResult::Reject);
+        assert_eq!(queue.capacity(), 1);
+        assert_eq!(
+            queue.receive(Seq16::new(1)),
+            ReceiveResult::RejectBeyondWindow
+        );
         let first = queue.pop();
         assert_eq!(first, Some(Seq16::new(0)));
         let first = queue.pop();
@@ -96,4 +303,270 @@ mod tests {
         let first = queue.pop();
         assert_eq!(first, None);
     }
+
+    #[test]
+    fn receive_range_partial() {
+        let mut queue = ReceiveQueue::new(3);
+        let results = queue.receive_range(Seq16::new(1), Seq16::new(4));
+        assert_eq!(
+            results,
+            vec![
+                (Seq16::new(1), ReceiveResult::AcceptDeliverable(0)),
+                (Seq16::new(2), ReceiveResult::AcceptDeliverable(0)),
+                (Seq16::new(3), ReceiveResult::RejectBeyondWindow),
+                (Seq16::new(4), ReceiveResult::RejectBeyondWindow),
+            ]
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(0)),
+            ReceiveResult::AcceptDeliverable(3)
+        );
+        assert_eq!(queue.pop(), Some(Seq16::new(0)));
+        assert_eq!(queue.pop(), Some(Seq16::new(1)));
+        assert_eq!(queue.pop(), Some(Seq16::new(2)));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn contains_window() {
+        let mut queue = ReceiveQueue::with_start(3, Seq16::new(u16::MAX));
+        assert_eq!(queue.window(), (Seq16::new(u16::MAX), Seq16::new(2)));
+        assert_eq!(
+            queue.receive(Seq16::new(0)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+        assert!(queue.contains(Seq16::new(0)));
+        assert!(!queue.contains(Seq16::new(u16::MAX)));
+
+        // Popping slides the window
+        assert_eq!(
+            queue.receive(Seq16::new(u16::MAX)),
+            ReceiveResult::AcceptDeliverable(2)
+        );
+        assert!(queue.contains(Seq16::new(u16::MAX)));
+        assert_eq!(queue.pop(), Some(Seq16::new(u16::MAX)));
+        assert!(!queue.contains(Seq16::new(u16::MAX)));
+        assert_eq!(queue.window(), (Seq16::new(0), Seq16::new(3)));
+        assert_eq!(queue.pop(), Some(Seq16::new(0)));
+        assert!(!queue.contains(Seq16::new(0)));
+        assert_eq!(queue.window(), (Seq16::new(1), Seq16::new(4)));
+    }
+
+    #[test]
+    fn pop_in_order() {
+        let mut queue = ReceiveQueue::new(8);
+        for seq in [1, 2, 5] {
+            assert_eq!(
+                queue.receive(Seq16::new(seq)),
+                ReceiveResult::AcceptDeliverable(0)
+            );
+        }
+        assert_eq!(queue.pop(), None);
+
+        // Fill the gap
+        assert_eq!(
+            queue.receive(Seq16::new(0)),
+            ReceiveResult::AcceptDeliverable(3)
+        );
+        assert_eq!(queue.pop(), Some(Seq16::new(0)));
+        assert_eq!(queue.pop(), Some(Seq16::new(1)));
+        assert_eq!(queue.pop(), Some(Seq16::new(2)));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.skipped().is_empty());
+    }
+
+    #[test]
+    fn pop_any() {
+        let mut queue = ReceiveQueue::new(8);
+        for seq in [1, 2, 5] {
+            assert_eq!(
+                queue.receive(Seq16::new(seq)),
+                ReceiveResult::AcceptDeliverable(0)
+            );
+        }
+        assert_eq!(queue.pop_any(), Some(Seq16::new(1)));
+        assert_eq!(queue.skipped(), &[Seq16::new(0)]);
+        assert_eq!(queue.pop_any(), Some(Seq16::new(2)));
+        assert_eq!(queue.pop_any(), Some(Seq16::new(5)));
+        assert_eq!(
+            queue.skipped(),
+            &[Seq16::new(0), Seq16::new(3), Seq16::new(4)]
+        );
+        assert_eq!(queue.pop_any(), None);
+
+        // Skipped sequences are too late to be received
+        assert_eq!(queue.receive(Seq16::new(3)), ReceiveResult::RejectTooOld);
+        assert_eq!(queue.window(), (Seq16::new(6), Seq16::new(14)));
+    }
+
+    #[test]
+    fn receive_across_wrap() {
+        let mut queue = ReceiveQueue::with_start(4, Seq16::new(65535));
+        assert_eq!(
+            queue.receive(Seq16::new(1)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(65535)),
+            ReceiveResult::AcceptDeliverable(1)
+        );
+        assert_eq!(queue.pop(), Some(Seq16::new(65535)));
+
+        // `0` is numerically below the new head but follows the old one
+        assert_eq!(
+            queue.receive(Seq16::new(0)),
+            ReceiveResult::AcceptDeliverable(2)
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(65535)),
+            ReceiveResult::RejectTooOld
+        );
+        assert_eq!(queue.pop(), Some(Seq16::new(0)));
+        assert_eq!(queue.pop(), Some(Seq16::new(1)));
+    }
+
+    #[test]
+    fn receive_near_wrap() {
+        let mut queue = ReceiveQueue::with_start(16, Seq16::new(65530));
+        assert_eq!(
+            queue.receive(Seq16::new(5)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(65529)),
+            ReceiveResult::RejectTooOld
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(10)),
+            ReceiveResult::RejectBeyondWindow
+        );
+    }
+
+    #[test]
+    fn high_watermark() {
+        let mut queue = ReceiveQueue::new(8);
+        for (seq, deliverable) in [(3, 0), (1, 0), (0, 2), (5, 0)] {
+            assert_eq!(
+                queue.receive(Seq16::new(seq)),
+                ReceiveResult::AcceptDeliverable(deliverable)
+            );
+        }
+        assert_eq!(queue.buffered_count(), 4);
+        assert_eq!(queue.high_watermark(), 4);
+
+        assert_eq!(queue.pop(), Some(Seq16::new(0)));
+        assert_eq!(queue.pop(), Some(Seq16::new(1)));
+        assert_eq!(queue.buffered_count(), 2);
+        assert_eq!(queue.high_watermark(), 4);
+
+        // Rejected packets are not counted
+        assert_eq!(queue.receive(Seq16::new(3)), ReceiveResult::RejectDuplicate);
+        assert_eq!(
+            queue.receive(Seq16::new(2)),
+            ReceiveResult::AcceptDeliverable(2)
+        );
+        assert_eq!(queue.high_watermark(), 4);
+
+        queue.reset_watermark();
+        assert_eq!(queue.high_watermark(), 3);
+        assert_eq!(queue.pop(), Some(Seq16::new(2)));
+        assert_eq!(queue.high_watermark(), 3);
+    }
+
+    #[test]
+    fn max_window() {
+        let mut queue = ReceiveQueue::new(MAX_WINDOW);
+        assert_eq!(queue.window(), (Seq16::new(0), Seq16::new(32768)));
+        assert_eq!(
+            queue.receive(Seq16::new(32767)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+
+        // Half the sequence space away is behind the window
+        assert_eq!(
+            queue.receive(Seq16::new(32768)),
+            ReceiveResult::RejectTooOld
+        );
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic)]
+    fn oversized_capacity() {
+        let mut queue = ReceiveQueue::new(u16::MAX as usize + 1);
+        assert_eq!(queue.window(), (Seq16::new(0), Seq16::new(32768)));
+        assert_eq!(
+            queue.receive(Seq16::new(40000)),
+            ReceiveResult::RejectTooOld
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let mut queue = ReceiveQueue::new(2);
+        assert_eq!(
+            queue.receive(Seq16::new(1)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+        queue.reset(Seq16::new(1000));
+        assert_eq!(queue.receive(Seq16::new(999)), ReceiveResult::RejectTooOld);
+        assert_eq!(queue.receive(Seq16::new(1)), ReceiveResult::RejectTooOld);
+        assert_eq!(queue.pop(), None);
+        assert_eq!(
+            queue.receive(Seq16::new(1000)),
+            ReceiveResult::AcceptDeliverable(1)
+        );
+        assert_eq!(queue.pop(), Some(Seq16::new(1000)));
+
+        let mut queue = ReceiveQueue::with_start(2, Seq16::new(1000));
+        assert_eq!(queue.receive(Seq16::new(999)), ReceiveResult::RejectTooOld);
+        assert_eq!(
+            queue.receive(Seq16::new(1001)),
+            ReceiveResult::AcceptDeliverable(0)
+        );
+        assert_eq!(
+            queue.receive(Seq16::new(1002)),
+            ReceiveResult::RejectBeyondWindow
+        );
+    }
+
+    #[test]
+    fn expire_gaps() {
+        let timeout = time::Duration::from_millis(100);
+        let mut queue = ReceiveQueue::<MockInstant>::with_timestamp(8, Seq16::new(0));
+        let now = MockInstant::default();
+        assert!(queue.expire_gaps(now, timeout).is_empty());
+        for seq in [2, 3, 5] {
+            assert_eq!(
+                queue.receive(Seq16::new(seq)),
+                ReceiveResult::AcceptDeliverable(0)
+            );
+        }
+        assert_eq!(queue.pop(), None);
+
+        // The gap is first seen here
+        assert!(queue.expire_gaps(now, timeout).is_empty());
+        let now = now + time::Duration::from_millis(50);
+        assert!(queue.expire_gaps(now, timeout).is_empty());
+        assert_eq!(queue.pop(), None);
+
+        let now = now + time::Duration::from_millis(50);
+        assert_eq!(
+            queue.expire_gaps(now, timeout),
+            vec![Seq16::new(0), Seq16::new(1)]
+        );
+        assert_eq!(queue.pop(), Some(Seq16::new(2)));
+        assert_eq!(queue.pop(), Some(Seq16::new(3)));
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.receive(Seq16::new(1)), ReceiveResult::RejectTooOld);
+
+        // The next gap is timed on its own
+        assert!(queue.expire_gaps(now, timeout).is_empty());
+        let now = now + timeout;
+        assert_eq!(queue.expire_gaps(now, timeout), vec![Seq16::new(4)]);
+        assert_eq!(queue.pop(), Some(Seq16::new(5)));
+        assert_eq!(
+            queue.skipped(),
+            &[Seq16::new(0), Seq16::new(1), Seq16::new(4)]
+        );
+    }
 }
@@ -0,0 +1,660 @@
+use ::core::hash::Hash;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+pub struct Scheduler<K> {
+    weight_vector: HashMap<K, f64>,
+    learning_rate: f64,
+    /// Every live key keeps at least this weight to stay probed
+    min_weight: f64,
+    learning_rate_mode: LearningRateMode,
+    /// The sum of squared gradients of each key
+    squared_gradient_sums: HashMap<K, f64>,
+}
+
+impl<K> Scheduler<K>
+where
+    K: Eq + Hash + Copy + Ord,
+{
+    #[must_use]
+    pub fn new_empty(learning_rate: f64) -> Self {
+        Self {
+            weight_vector: HashMap::new(),
+            learning_rate,
+            min_weight: 0.0,
+            learning_rate_mode: LearningRateMode::Fixed,
+            squared_gradient_sums: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn new(fd_vector: impl Iterator<Item = K>, learning_rate: f64) -> Self {
+        let mut this = Self {
+            weight_vector: HashMap::new(),
+            learning_rate,
+            min_weight: 0.0,
+            learning_rate_mode: LearningRateMode::Fixed,
+            squared_gradient_sums: HashMap::new(),
+        };
+
+        // Init weight vector
+        this.init_weight(fd_vector);
+
+        this
+    }
+
+    fn init_weight(&mut self, fds: impl Iterator<Item = K>) {
+        for key in fds {
+            self.weight_vector.insert(key, 1.0);
+        }
+        let even_weight = 1.0 / self.weight_vector.len() as f64;
+        for weight in self.weight_vector.values_mut() {
+            *weight = even_weight;
+        }
+    }
+
+    /// Do not include RTTs that are either infinite, NaN, or time out.
+    pub fn update(&mut self, rtt_vector: &HashMap<K, f64>) {
+        if self.weight_vector.len() == 0 {
+            // Init weight vector
+            self.init_weight(rtt_vector.keys().copied());
+        }
+
+        // The sole socket takes all the weight
+        if rtt_vector.len() == 1 {
+            self.weight_vector = rtt_vector.keys().map(|key| (*key, 1.0)).collect();
+            return;
+        }
+
+        let clean_rtt_vector = normalize(rtt_vector);
+
+        // Get minimum RTT index
+        let Some(min_rtt_index) = arg_min_key(clean_rtt_vector.iter()) else {
+            // `rtt_vector` is empty
+            return;
+        };
+
+        // To remove dead fds from the next weight vector
+        let mut next_weight_vector = HashMap::new();
+
+        // Update weight vector
+        for (key, rtt) in clean_rtt_vector.iter() {
+            // Get current weight
+            let weight = self.weight(key).unwrap();
+
+            // Calculate partial derivative
+            let partial_derivative = match key == min_rtt_index {
+                true => -*rtt,
+                false => *rtt,
+            };
+
+            // Scale the learning rate of the key
+            let learning_rate = match self.learning_rate_mode {
+                LearningRateMode::Fixed => self.learning_rate,
+                LearningRateMode::AdaGrad { epsilon } => {
+                    let sum = self.squared_gradient_sums.entry(*key).or_insert(0.0);
+                    *sum += partial_derivative * partial_derivative;
+                    self.learning_rate / (sqrt(*sum) + epsilon)
+                }
+            };
+
+            // Nudge the weight in the opposite direction of the gradient
+            let mut next_weight = weight - learning_rate * partial_derivative;
+
+            // Prevent negative weight
+            if next_weight < 0.0 {
+                next_weight = 0.0;
+            }
+
+            // Store next weight
+            next_weight_vector.insert(*key, next_weight);
+        }
+
+        // Normalize weight vector
+        normalize_mut(&mut next_weight_vector);
+
+        // Keep every weight above the floor
+        clamp_min_mut(&mut next_weight_vector, self.min_weight);
+
+        // Forget the gradients of dead keys
+        self.squared_gradient_sums
+            .retain(|key, _| next_weight_vector.contains_key(key));
+
+        // Store weight vector
+        self.weight_vector = next_weight_vector;
+    }
+
+    #[must_use]
+    pub fn weights(&self) -> &HashMap<K, f64> {
+        &self.weight_vector
+    }
+
+    /// The Shannon entropy of the weight vector.
+    ///
+    /// It is `ln(n)` if the weights are even and `0` if one key takes all the weight.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn entropy(&self) -> f64 {
+        let sum: f64 = self.weight_vector.values().sum();
+        let mut entropy = 0.0;
+        for weight in self.weight_vector.values() {
+            let p = *weight / sum;
+            if p > 0.0 {
+                entropy -= p * p.ln();
+            }
+        }
+        entropy
+    }
+
+    /// Give every current key the same weight.
+    ///
+    /// Near-zero weights barely move, so this speeds up the recovery from a collapsed weight vector.
+    /// The accumulated gradients are reset as well.
+    pub fn reset_to_even(&mut self) {
+        let keys = self.weight_vector.keys().copied().collect::<Vec<_>>();
+        self.weight_vector.clear();
+        self.init_weight(keys.into_iter());
+        self.squared_gradient_sums.clear();
+    }
+
+    /// Replace the weight vector, e.g. to restore the weights of a migrated connection.
+    ///
+    /// The weights are normalized and the accumulated gradients are reset.
+    pub fn set_weights(&mut self, weights: HashMap<K, f64>) {
+        self.weight_vector = weights;
+        if !self.weight_vector.is_empty() {
+            normalize_mut(&mut self.weight_vector);
+        }
+        self.squared_gradient_sums.clear();
+    }
+
+    /// Take effect on the next `update`.
+    pub fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+
+    pub fn set_min_weight(&mut self, min_weight: f64) {
+        self.min_weight = min_weight;
+    }
+
+    /// Also reset the accumulated gradients.
+    pub fn set_learning_rate_mode(&mut self, learning_rate_mode: LearningRateMode) {
+        self.learning_rate_mode = learning_rate_mode;
+        self.squared_gradient_sums.clear();
+    }
+
+    #[must_use]
+    pub fn weight(&self, key: &K) -> Option<f64> {
+        if self.weight_vector.len() == 0 {
+            // A valid weight vector cannot be empty
+            return None;
+        }
+        let weight = match self.weight_vector.get(key) {
+            Some(weight) => *weight,
+            None => 0.0, // New FD
+        };
+        if !weight.is_finite() {
+            // Corrupted by the floating-point arithmetic
+            return Some(1.0 / self.weight_vector.len() as f64);
+        }
+        Some(weight)
+    }
+}
+
+/// How the learning rate of each key is scaled on update
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LearningRateMode {
+    /// Every key learns at `learning_rate`
+    Fixed,
+    /// AdaGrad: each key learns at `learning_rate / (sqrt(sum) + epsilon)`, where `sum` is the sum of its squared gradients
+    AdaGrad { epsilon: f64 },
+}
+
+impl LearningRateMode {
+    /// The default `epsilon` of `AdaGrad`, only there to avoid dividing by zero
+    pub const DEFAULT_EPSILON: f64 = 1e-8;
+
+    /// `AdaGrad` with `DEFAULT_EPSILON`
+    #[must_use]
+    pub fn ada_grad() -> Self {
+        Self::AdaGrad {
+            epsilon: Self::DEFAULT_EPSILON,
+        }
+    }
+}
+
+/// `f64::sqrt` is only in `std`.
+#[must_use]
+fn sqrt(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    {
+        x.sqrt()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        libm::sqrt(x)
+    }
+}
+
+#[must_use]
+#[allow(dead_code)]
+fn normalize<K>(vector: &HashMap<K, f64>) -> HashMap<K, f64>
+where
+    K: Eq + Hash + Copy,
+{
+    let mut normalized_vector = vector.clone();
+    normalize_mut(&mut normalized_vector);
+    normalized_vector
+}
+
+#[cfg(feature = "std")]
+#[must_use]
+#[allow(dead_code)]
+fn standardize<K>(vector: &HashMap<K, f64>) -> Result<HashMap<K, f64>, StandardizeError>
+where
+    K: Eq + Hash + Copy,
+{
+    if vector.len() < 2 {
+        return Err(StandardizeError::TooFewSamples);
+    }
+    let mut standardized_vector = HashMap::new();
+    let mean: f64 = vector.values().sum::<f64>() / vector.len() as f64;
+    let mut sum_of_squares = 0.0;
+    for weight in vector.values() {
+        sum_of_squares += (weight - mean).powi(2);
+    }
+    if sum_of_squares == 0.0 {
+        return Err(StandardizeError::ZeroStdDev);
+    }
+    let std_dev = (sum_of_squares / (vector.len() - 1) as f64).sqrt();
+    for (key, weight) in vector {
+        standardized_vector.insert(*key, (*weight - mean) / std_dev);
+    }
+    Ok(standardized_vector)
+}
+
+#[cfg(feature = "std")]
+enum StandardizeError {
+    ZeroStdDev,
+    TooFewSamples,
+}
+
+/// Fall back to even weights if the sum is zero or not finite, which would otherwise spread `NaN` or `Inf`.
+fn normalize_mut<K>(vector: &mut HashMap<K, f64>) {
+    let sum: f64 = vector.values().sum();
+    if !(sum.is_finite() && sum > 0.0) {
+        let even_weight = 1.0 / vector.len() as f64;
+        for weight in vector.values_mut() {
+            *weight = even_weight;
+        }
+        return;
+    }
+    for weight in vector.values_mut() {
+        *weight /= sum;
+    }
+}
+
+/// Raise every weight to at least `min_weight` and scale the rest so that the vector stays normalized.
+fn clamp_min_mut<K>(vector: &mut HashMap<K, f64>, min_weight: f64)
+where
+    K: Eq + Hash + Copy,
+{
+    if min_weight <= 0.0 || vector.is_empty() {
+        return;
+    }
+    if min_weight * vector.len() as f64 >= 1.0 {
+        // The floor leaves no room for preference
+        let even_weight = 1.0 / vector.len() as f64;
+        for weight in vector.values_mut() {
+            *weight = even_weight;
+        }
+        return;
+    }
+
+    let mut floored = HashSet::new();
+    loop {
+        // Share left to the weights above the floor
+        let free_share = 1.0 - floored.len() as f64 * min_weight;
+        let free_sum: f64 = vector
+            .iter()
+            .filter(|(key, _)| !floored.contains(*key))
+            .map(|(_, weight)| *weight)
+            .sum();
+        let free_count = vector.len() - floored.len();
+
+        // Floor the weights that would fall below it
+        let mut next_floored = Vec::new();
+        for (key, weight) in vector.iter() {
+            if floored.contains(key) {
+                continue;
+            }
+            let scaled = match free_sum > 0.0 {
+                true => *weight / free_sum * free_share,
+                false => free_share / free_count as f64,
+            };
+            if scaled < min_weight {
+                next_floored.push(*key);
+            }
+        }
+        if !next_floored.is_empty() {
+            for key in next_floored {
+                vector.insert(key, min_weight);
+                floored.insert(key);
+            }
+            continue;
+        }
+
+        // Scale the rest
+        for (key, weight) in vector.iter_mut() {
+            if floored.contains(key) {
+                continue;
+            }
+            *weight = match free_sum > 0.0 {
+                true => *weight / free_sum * free_share,
+                false => free_share / free_count as f64,
+            };
+        }
+        return;
+    }
+}
+
+/// Ties go to the smallest key so the result does not depend on the iteration order.
+#[must_use]
+fn arg_min_key<'a, K, I>(vector: I) -> Option<&'a K>
+where
+    K: Ord,
+    I: Iterator<Item = (&'a K, &'a f64)>,
+{
+    let mut min_key: Option<&K> = None;
+    let mut min_value = f64::MAX;
+    for (key, value) in vector {
+        let is_min = match min_key {
+            Some(min_key) => *value < min_value || (*value == min_value && key < min_key),
+            None => *value < min_value,
+        };
+        if is_min {
+            min_value = *value;
+            min_key = Some(key);
+        }
+    }
+    min_key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut scheduler = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        assert_eq!(scheduler.weight(&0).unwrap(), 1.0 / 3.0);
+        assert_eq!(scheduler.weight(&1).unwrap(), 1.0 / 3.0);
+        assert_eq!(scheduler.weight(&2).unwrap(), 1.0 / 3.0);
+
+        let prev_weight_vector = scheduler.weight_vector.clone();
+
+        // Update weight vector
+        scheduler.update(
+            &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        println!("1st: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() > prev_weight_vector[&0]);
+        assert!(scheduler.weight(&1).unwrap() < prev_weight_vector[&1]);
+        assert!(scheduler.weight(&2).unwrap() < prev_weight_vector[&2]);
+
+        let prev_weight_vector = scheduler.weight_vector.clone();
+
+        // Update weight vector
+        scheduler.update(
+            &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        println!("2nd: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() > prev_weight_vector[&0]);
+        assert!(scheduler.weight(&1).unwrap() < prev_weight_vector[&1]);
+        assert!(scheduler.weight(&2).unwrap() < prev_weight_vector[&2]);
+
+        let _prev_weight_vector = scheduler.weight_vector.clone();
+
+        // Converge final weight vector
+        for _ in 0..100 {
+            scheduler.update(
+                &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        println!("102th: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() > 0.999);
+        assert!(scheduler.weight(&1).unwrap() < 0.001);
+        assert!(scheduler.weight(&2).unwrap() < 0.001);
+
+        let prev_weight_vector = scheduler.weight_vector.clone();
+
+        // RTTs swap
+        scheduler.update(
+            &vec![(0, 300.0), (1, 200.0), (2, 100.0)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        println!("103th: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() < prev_weight_vector[&0]);
+        assert!(f64::abs(scheduler.weight(&1).unwrap() - prev_weight_vector[&1]) < 0.001);
+        assert!(scheduler.weight(&2).unwrap() > prev_weight_vector[&2]);
+    }
+
+    #[test]
+    fn ada_grad() {
+        let mut scheduler = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        scheduler.set_learning_rate_mode(LearningRateMode::ada_grad());
+
+        // Converge weight vector
+        for _ in 0..100 {
+            scheduler.update(
+                &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        assert!(scheduler.weight(&0).unwrap() > 0.999);
+        assert!(scheduler.weight(&1).unwrap() < 0.001);
+        assert!(scheduler.weight(&2).unwrap() < 0.001);
+
+        let prev_weight_vector = scheduler.weight_vector.clone();
+
+        // RTTs swap
+        scheduler.update(
+            &vec![(0, 300.0), (1, 200.0), (2, 100.0)]
+                .into_iter()
+                .collect(),
+        );
+        assert!(scheduler.weight(&0).unwrap() < prev_weight_vector[&0]);
+        assert!(f64::abs(scheduler.weight(&1).unwrap() - prev_weight_vector[&1]) < 0.001);
+        assert!(scheduler.weight(&2).unwrap() > prev_weight_vector[&2]);
+
+        // Dead keys forget their gradients
+        scheduler.update(&vec![(0, 300.0), (2, 100.0)].into_iter().collect());
+        assert!(!scheduler.squared_gradient_sums.contains_key(&1));
+    }
+
+    #[test]
+    fn reset_to_even() {
+        let rtt_vector = vec![(0, 100.0), (1, 200.0)].into_iter().collect();
+        let mut scheduler = Scheduler::new(vec![0, 1].into_iter(), 0.5);
+        for _ in 0..10 {
+            scheduler.update(&rtt_vector);
+        }
+        assert_eq!(scheduler.weight(&1), Some(0.0));
+
+        scheduler.reset_to_even();
+        assert_eq!(scheduler.weight(&0), Some(0.5));
+        assert_eq!(scheduler.weight(&1), Some(0.5));
+    }
+
+    #[test]
+    fn set_learning_rate() {
+        let rtt_vector = vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+            .into_iter()
+            .collect();
+        let mut slow = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        let mut fast = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        fast.set_learning_rate(0.3);
+        slow.update(&rtt_vector);
+        fast.update(&rtt_vector);
+        assert!(fast.weight(&0).unwrap() > slow.weight(&0).unwrap());
+        assert!(fast.weight(&2).unwrap() < slow.weight(&2).unwrap());
+
+        // Slow down again
+        let prev_weight = fast.weight(&0).unwrap();
+        fast.set_learning_rate(0.01);
+        fast.update(&rtt_vector);
+        let fast_step = fast.weight(&0).unwrap() - prev_weight;
+        assert!(fast_step > 0.0);
+        assert!(fast_step < 0.01);
+    }
+
+    #[test]
+    fn fd_removal() {
+        let mut scheduler = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        assert_eq!(scheduler.weight(&0).unwrap(), 1.0 / 3.0);
+        assert_eq!(scheduler.weight(&1).unwrap(), 1.0 / 3.0);
+        assert_eq!(scheduler.weight(&2).unwrap(), 1.0 / 3.0);
+
+        // Update weight vector
+        scheduler.update(&vec![(0, 100.0), (1, 200.0)].into_iter().collect());
+        assert_eq!(scheduler.weight_vector.len(), 2);
+        println!("1st: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() > 0.5);
+        assert!(scheduler.weight(&1).unwrap() < 0.5);
+    }
+
+    #[test]
+    fn fd_addition() {
+        let mut scheduler = Scheduler::new(vec![0, 1].into_iter(), 0.1);
+        assert_eq!(scheduler.weight_vector.len(), 2);
+        assert_eq!(scheduler.weight(&0).unwrap(), 1.0 / 2.0);
+        assert_eq!(scheduler.weight(&1).unwrap(), 1.0 / 2.0);
+
+        // Update weight vector
+        scheduler.update(
+            &vec![(0, 100.0), (1, 300.0), (2, 200.0)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        println!("1st: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() > 1.0 / 3.0);
+        assert!(scheduler.weight(&1).unwrap() > scheduler.weight(&2).unwrap());
+        assert_eq!(scheduler.weight(&2).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn from_empty_ok() {
+        let mut scheduler = Scheduler::new_empty(0.1);
+        assert_eq!(scheduler.weight_vector.len(), 0);
+
+        // Update weight vector
+        scheduler.update(
+            &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(scheduler.weight_vector.len(), 3);
+        assert!(scheduler.weight(&0).unwrap() > 1.0 / 3.0);
+        assert!(scheduler.weight(&1).unwrap() < 1.0 / 3.0);
+        assert!(scheduler.weight(&2).unwrap() < 1.0 / 3.0);
+    }
+
+    #[test]
+    fn single_fd() {
+        let mut scheduler = Scheduler::new(vec![0, 1].into_iter(), 0.1);
+        assert_eq!(scheduler.weight(&0).unwrap(), 1.0 / 2.0);
+
+        // Update weight vector
+        scheduler.update(&vec![(0, 100.0)].into_iter().collect());
+        assert_eq!(scheduler.weight_vector.len(), 1);
+        assert_eq!(scheduler.weight(&0).unwrap(), 1.0);
+        assert_eq!(scheduler.weight(&1).unwrap(), 0.0);
+
+        // A zero RTT must not poison the weight
+        let mut scheduler = Scheduler::new_empty(0.1);
+        scheduler.update(&vec![(0, 0.0)].into_iter().collect());
+        assert_eq!(scheduler.weight(&0).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn min_weight() {
+        let min_weight = 0.05;
+        let mut scheduler = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        scheduler.set_min_weight(min_weight);
+
+        // Converge final weight vector
+        for _ in 0..100 {
+            scheduler.update(
+                &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                    .into_iter()
+                    .collect(),
+            );
+            for weight in scheduler.weight_vector.values() {
+                assert!(*weight >= min_weight);
+            }
+            let sum: f64 = scheduler.weight_vector.values().sum();
+            assert!(f64::abs(sum - 1.0) < 1e-9);
+        }
+        println!("100th: {:?}", scheduler.weight_vector);
+        assert!(scheduler.weight(&0).unwrap() > 0.89);
+        assert_eq!(scheduler.weight(&1).unwrap(), min_weight);
+        assert_eq!(scheduler.weight(&2).unwrap(), min_weight);
+    }
+
+    #[test]
+    fn entropy() {
+        let mut scheduler = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        assert_eq!(scheduler.weights().len(), 3);
+        assert!(f64::abs(scheduler.entropy() - f64::ln(3.0)) < 1e-9);
+
+        // Converge final weight vector
+        for _ in 0..100 {
+            scheduler.update(
+                &vec![(0, 100.0), (1, 200.0), (2, 300.0)]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        println!("100th: {:?}", scheduler.weights());
+        assert!(scheduler.entropy() < 0.01);
+
+        let scheduler = Scheduler::<i32>::new_empty(0.1);
+        assert_eq!(scheduler.entropy(), 0.0);
+    }
+
+    #[test]
+    fn zero_rtts() {
+        let mut scheduler = Scheduler::new(vec![0, 1, 2].into_iter(), 0.1);
+        for _ in 0..10 {
+            scheduler.update(&vec![(0, 0.0), (1, 0.0), (2, 0.0)].into_iter().collect());
+            for weight in scheduler.weights().values() {
+                assert!(weight.is_finite());
+            }
+            let sum: f64 = scheduler.weights().values().sum();
+            assert!(f64::abs(sum - 1.0) < 1e-9);
+        }
+
+        // A corrupted weight reads as the even share
+        scheduler.weight_vector.insert(0, f64::NAN);
+        assert_eq!(scheduler.weight(&0), Some(1.0 / 3.0));
+    }
+}
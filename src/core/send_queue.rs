@@ -1,26 +1,45 @@
-use std::collections::BTreeSet;
+use alloc::collections::BTreeSet;
 
 use seq::Seq16;
 
+use super::clamp_window;
+
 pub struct SendQueue {
     /// The queue of sending packets
     queue: BTreeSet<Seq16>,
-    /// The maximum number of packets that can be stored in the queue
+    /// The maximum number of packets that can be stored in the queue, at most `MAX_WINDOW`
     capacity: usize,
     /// The sequence number of the next new packet
     shadow_end: Seq16,
 }
 
 impl SendQueue {
+    /// `capacity` saturates at `MAX_WINDOW`.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `capacity` exceeds `MAX_WINDOW`.
     #[must_use]
     pub fn new(capacity: usize) -> Self {
+        Self::with_start(capacity, Seq16::new(0))
+    }
+
+    /// Same as `new` but the first packet takes `shadow_end`, so each connection can start from its own sequence.
+    #[must_use]
+    pub fn with_start(capacity: usize, shadow_end: Seq16) -> Self {
         Self {
             queue: BTreeSet::new(),
-            capacity,
-            shadow_end: Seq16::new(0),
+            capacity: clamp_window(capacity),
+            shadow_end,
         }
     }
 
+    /// Drop all unacknowledged packets and continue from `shadow_end`.
+    pub fn reset(&mut self, shadow_end: Seq16) {
+        self.queue.clear();
+        self.shadow_end = shadow_end;
+    }
+
     #[must_use]
     pub fn send(&mut self) -> Option<Seq16> {
         // Reject if the queue is full
@@ -42,14 +61,38 @@ impl SendQueue {
         self.queue.remove(&seq);
     }
 
+    /// Same as `new` on `capacity`.
     pub fn set_capacity(&mut self, capacity: usize) {
-        self.capacity = capacity;
+        self.capacity = clamp_window(capacity);
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The sequence number the next new packet takes
+    #[must_use]
+    pub fn next_seq(&self) -> Seq16 {
+        self.shadow_end
+    }
+
+    /// The number of packets sent but not yet acknowledged
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::MAX_WINDOW;
 
     #[test]
     fn ok() {
@@ -82,4 +125,24 @@ mod tests {
         assert_eq!(queue.send(), Some(Seq16::new(2)));
         assert_eq!(queue.send(), None);
     }
+
+    #[test]
+    fn with_start() {
+        let mut queue = SendQueue::with_start(2, Seq16::new(40000));
+        assert_eq!(queue.send(), Some(Seq16::new(40000)));
+        assert_eq!(queue.send(), Some(Seq16::new(40001)));
+        assert_eq!(queue.send(), None);
+
+        queue.reset(Seq16::new(u16::MAX));
+        assert!(queue.is_empty());
+        assert_eq!(queue.send(), Some(Seq16::new(u16::MAX)));
+        assert_eq!(queue.send(), Some(Seq16::new(0)));
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic)]
+    fn oversized_capacity() {
+        let queue = SendQueue::new(u16::MAX as usize + 1);
+        assert_eq!(queue.capacity(), MAX_WINDOW);
+    }
 }
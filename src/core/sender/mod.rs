@@ -1,36 +1,169 @@
+mod rng;
+mod rtt_estimator;
 mod rtt_stopwatch;
-mod scheduler;
-mod send_queue;
+mod snapshot;
 mod sockets;
 mod timed_send_queue;
+mod weigher;
 
-use std::{collections::HashMap, os::fd::RawFd, time};
+use std::{
+    collections::{BTreeSet, HashMap},
+    os::fd::RawFd,
+    time,
+};
 
+use rep::*;
+
+use super::{seq_range, SeqOrder, MAX_WINDOW};
+pub use super::{Clock, MockClock, MockInstant, SystemClock, Timestamp};
+pub use super::{LearningRateMode, Scheduler, SendQueue};
+pub use rng::*;
+pub use rtt_estimator::*;
 pub use rtt_stopwatch::*;
-pub use scheduler::*;
-pub use send_queue::*;
 use seq::Seq16;
+pub use snapshot::*;
+use thiserror::Error;
 pub use timed_send_queue::*;
+pub use weigher::*;
+
+use crate::frame::{Ack, Frame, Payload};
 
-use self::sockets::{Credit, ReassignPayloadError, RetransmitPayloads, Sockets};
+pub use self::sockets::{Credit, Socket};
+pub use self::sockets::{ReassignPayloadError, RetransmitPayloads};
+use self::sockets::{Sockets, DEFAULT_RTT_HISTORY};
 
-pub struct Sender {
-    sockets: Sockets,
+/// Called with `(fd, old credit, new credit)` on each credit transition
+type CreditObserver = Box<dyn FnMut(RawFd, Credit, Credit)>;
+
+pub struct Sender<T = time::Instant> {
+    sockets: Sockets<T>,
     scheduler: Scheduler<RawFd>,
-    payload_queue: TimedSendQueue<RawFd>,
+    payload_queue: TimedSendQueue<RawFd, T>,
+    /// Payload sizes assigned to each socket on the most recent `send`
+    last_apportionment: HashMap<RawFd, usize>,
+    /// Duplicate acks counted for the current cumulative point
+    dup_acks: Option<DupAcks>,
+    /// Payloads left without a socket by `remove_fd` and no longer timed
+    orphaned: BTreeSet<Seq16>,
+    /// The size of each payload not yet acked
+    payload_sizes: HashMap<Seq16, usize>,
+    /// Counters since creation
+    metrics: SendMetrics,
+    credit_observer: Option<CreditObserver>,
+    /// The last time a payload was sent or probed
+    last_send: Option<T>,
+    /// The last time an ack was received
+    last_ack: Option<T>,
+    /// Payloads the peer can still take, or `None` if it has not advertised a window
+    peer_window: Option<usize>,
 
     default_rto: time::Duration,
+    dup_ack_threshold: usize,
+    tail_loss_probe: bool,
+    reset_weights_on_discredit: bool,
+    /// Costs the credible sockets for the scheduler
+    weigher: Box<dyn Weigher<T>>,
+    scheduler_update_interval: time::Duration,
+    /// The last time an ack updated the scheduler
+    last_scheduler_update: Option<T>,
+    /// The config as created and amended by the setters, kept for `snapshot`
+    config: SendConfig,
+}
+
+impl<T> CheckFields for Sender<T>
+where
+    T: Timestamp,
+{
+    fn check_fields(&self, e: &mut RepErrors) {
+        // Check payload-queue-to-socket consistency
+        for seq in self.payload_queue.pending_sequences() {
+            if self.sockets.payload_fd(seq).is_none() {
+                e.add(format!(
+                    "Payload {:?} is timed, but payload {:?} is not assigned to any socket",
+                    seq, seq
+                ));
+            }
+        }
+
+        // Check socket-to-payload-queue consistency
+        for (fd, socket) in self.sockets.sockets().iter() {
+            for seq in socket.payloads().iter() {
+                if self.payload_queue.rtt_stopwatch(*seq).is_none() {
+                    e.add(format!(
+                        "Socket {} has payload {:?}, but payload {:?} is not timed",
+                        fd, seq, seq
+                    ));
+                }
+            }
+        }
+
+        // Orphaned payloads are neither timed nor assigned
+        for seq in self.orphaned.iter() {
+            if self.payload_queue.rtt_stopwatch(*seq).is_some()
+                || self.sockets.payload_fd(*seq).is_some()
+            {
+                e.add(format!(
+                    "Payload {:?} is orphaned, but payload {:?} is still timed or assigned",
+                    seq, seq
+                ));
+            }
+        }
+    }
 }
 
-impl Sender {
+impl<T> CheckRep for Sender<T> where T: Timestamp {}
+
+#[check_rep]
+impl<T> Sender<T>
+where
+    T: Timestamp,
+{
+    /// # Panics
+    ///
+    /// Panics if `config` is invalid.
     #[must_use]
     pub fn new(config: SendConfig) -> Self {
-        Self {
-            sockets: Sockets::new(),
-            scheduler: Scheduler::new(Vec::new().into_iter(), config.learning_rate),
+        match Self::try_new(config) {
+            Ok(this) => this,
+            Err(e) => panic!("invalid send config: {e}"),
+        }
+    }
+
+    pub fn try_new(config: SendConfig) -> Result<Self, SendConfigError> {
+        config.validate()?;
+
+        let mut scheduler = Scheduler::new(Vec::new().into_iter(), config.learning_rate);
+        scheduler.set_min_weight(config.min_weight);
+        scheduler.set_learning_rate_mode(config.learning_rate_mode);
+        let mut sockets = match config.rng_seed {
+            Some(seed) => Sockets::with_rng(SplitMix64::new(seed)),
+            None => Sockets::new(),
+        };
+        sockets.set_credit_hysteresis(config.credit_samples, config.discredit_rtos);
+        sockets.set_rtt_smoothing(config.rtt_smoothing);
+        sockets.set_rtt_history(config.rtt_history);
+        Ok(Self {
+            sockets,
+            scheduler,
             payload_queue: TimedSendQueue::new(config.payload_queue_size),
+            last_apportionment: HashMap::new(),
+            dup_acks: None,
+            orphaned: BTreeSet::new(),
+            payload_sizes: HashMap::new(),
+            metrics: SendMetrics::default(),
+            credit_observer: None,
+            last_send: None,
+            last_ack: None,
+            peer_window: None,
             default_rto: config.default_rto,
-        }
+            dup_ack_threshold: config.dup_ack_threshold,
+            tail_loss_probe: config.tail_loss_probe,
+            reset_weights_on_discredit: config.reset_weights_on_discredit,
+            weigher: Box::new(config.scheduler_input),
+            scheduler_update_interval: config.scheduler_update_interval,
+            last_scheduler_update: None,
+            config,
+        })
     }
 
     pub fn add_fd(&mut self, fd: RawFd) {
@@ -40,20 +173,93 @@ impl Sender {
     }
 
     /// Ignoring the error causes data loss.
-    #[must_use]
+    ///
+    /// The payloads orphaned by removing the last socket stop timing out until `reenqueue_orphaned`.
+    #[must_use = "ignoring the error causes data loss"]
     pub fn remove_fd(&mut self, fd: RawFd) -> Result<RetransmitPayloads, ReassignPayloadError> {
-        let res = self.sockets.remove_fd(fd);
+        let res = self.sockets.remove_fd(fd, self.scheduler.weights());
+        self.count_reassignments(&res);
+
+        // Stop the stopwatches of orphaned payloads to avoid phantom RTOs
+        // The payloads include those taken over from sockets removed earlier, whose stopwatches carry other keys
+        if let Err(ReassignPayloadError::NoSocketsLeft { payloads }) = &res {
+            for seq in payloads {
+                self.payload_queue.pause(*seq);
+                self.orphaned.insert(*seq);
+            }
+        }
 
         self.update_scheduler();
 
         res
     }
 
+    /// Reassign the payloads carried by a `ReassignPayloadError::NoSocketsLeft` to the current credible sockets.
+    ///
+    /// Payloads that have been acked in the meantime are skipped.
+    /// The payloads stay orphaned if there is no credible socket.
+    /// The reassigned payloads start timing out again from `now`.
+    pub fn reenqueue_orphaned(
+        &mut self,
+        now: T,
+        payloads: Vec<Seq16>,
+    ) -> Result<RetransmitPayloads, ReassignPayloadError> {
+        let payloads = payloads
+            .into_iter()
+            .filter(|seq| self.orphaned.contains(seq))
+            .collect::<Vec<_>>();
+        let res = self
+            .sockets
+            .reassign_orphaned_payloads(payloads, self.scheduler.weights());
+        self.count_reassignments(&res);
+
+        // Restart the stopwatches
+        if let Ok(retx) = &res {
+            for (fd, seq) in retx {
+                self.orphaned.remove(seq);
+                let timeout = self.sockets.sockets()[fd]
+                    .rto()
+                    .unwrap_or_else(|| self.fallback_rto());
+                self.payload_queue.resume(*seq, now, timeout, *fd);
+            }
+        }
+
+        res
+    }
+
     /// `payload_size` is not guaranteed to be fully sent.
     #[must_use]
-    pub fn send(&mut self, now: time::Instant, payload_size: usize) -> Vec<SendFrame> {
+    pub fn send(&mut self, now: T, payload_size: usize) -> Vec<SendFrame> {
+        let (frames, _) = self.send_with_backpressure(now, payload_size);
+        frames
+    }
+
+    /// Same as `send` but also return the bytes of `payload_size` that could not be scheduled.
+    ///
+    /// The caller should retry those bytes later, e.g. after acks free up the payload queue.
+    #[must_use]
+    pub fn send_with_backpressure(
+        &mut self,
+        now: T,
+        payload_size: usize,
+    ) -> (Vec<SendFrame>, usize) {
         // Assign payloads to sockets
-        let (pings, payloads) = self.assign_send(payload_size);
+        let (pings, mut payloads, mut unscheduled) = self.assign_send(payload_size);
+
+        // Keep the largest payloads within the peer window
+        if let Some(available) = self.peer_window {
+            payloads.sort_by(|(a_fd, a, _), (b_fd, b, _)| b.cmp(a).then(a_fd.cmp(b_fd)));
+            let available = usize::min(available, payloads.len());
+            for (_, payload_size, _) in payloads.drain(available..) {
+                unscheduled += payload_size;
+            }
+        }
+
+        // Record the apportionment
+        self.last_apportionment = payloads
+            .iter()
+            .map(|(fd, payload_size, _)| (*fd, *payload_size))
+            .collect();
 
         // Collect frames
         let mut frames = Vec::new();
@@ -61,6 +267,7 @@ impl Sender {
         // Send pings
         for fd in pings {
             if let Some(seq) = self.sockets.send_ping(fd, now) {
+                self.metrics.pings_sent += 1;
                 frames.push(SendFrame::Ping(PingSendFrame { fd, seq }));
             }
         }
@@ -68,40 +275,107 @@ impl Sender {
         // Send payloads
         for (fd, payload_size, timeout) in payloads {
             if let Some(seq) = self.payload_queue.send(now, timeout, fd) {
-                self.sockets.send_payload(fd, seq);
+                self.sockets.send_payload(fd, seq, now);
+                self.payload_sizes.insert(seq, payload_size);
+                self.metrics.payloads_sent += 1;
+                self.last_send = Some(now);
+                if let Some(available) = &mut self.peer_window {
+                    *available -= 1;
+                }
                 frames.push(SendFrame::Payload(PayloadSendFrame {
                     fd,
                     seq,
                     payload_size,
                 }));
+            } else {
+                // The payload queue is full
+                unscheduled += payload_size;
             }
         }
 
-        frames
+        (frames, unscheduled)
+    }
+
+    /// Same as `send` but pick the largest payload size whose frames fit in one datagram of `mtu` bytes.
+    ///
+    /// Each payload frame takes `PAYLOAD_FRAME_OVERHEAD` bytes on top of its data and each ping takes `PING_FRAME_LEN` bytes.
+    /// The pings alone may still exceed a tiny `mtu`.
+    #[must_use]
+    pub fn send_within(&mut self, now: T, mtu: usize) -> Vec<SendFrame> {
+        // The framed length grows with the payload size, so search for the largest fitting one
+        let mut fits = 0;
+        let mut exceeds = mtu + 1;
+        while fits + 1 < exceeds {
+            let payload_size = fits + (exceeds - fits) / 2;
+            match self.framed_len(payload_size) <= mtu {
+                true => fits = payload_size,
+                false => exceeds = payload_size,
+            }
+        }
+        self.send(now, fits)
+    }
+
+    /// The encoded length of the frames `send` would produce for `payload_size`
+    fn framed_len(&self, payload_size: usize) -> usize {
+        let shares = self.apportion(payload_size);
+        self.sockets
+            .sockets()
+            .iter()
+            .map(|(fd, socket)| match shares[fd] {
+                0 => match socket.has_pending_ping() {
+                    true => 0,
+                    false => PING_FRAME_LEN,
+                },
+                share => PAYLOAD_FRAME_OVERHEAD + share,
+            })
+            .sum()
+    }
+
+    /// Cap the payloads `send` schedules to the window `available` advertised by the peer, e.g. in a `WindowUpdate` frame.
+    ///
+    /// Each payload sent takes one unit of the window until the peer advertises it again.
+    pub fn set_peer_window(&mut self, available: usize) {
+        self.peer_window = Some(available);
+    }
+
+    /// The total size of the payloads not yet acked, including the ones orphaned by `remove_fd`.
+    #[must_use]
+    pub fn in_flight_bytes(&self) -> usize {
+        self.payload_sizes.values().sum()
+    }
+
+    /// The total size of the payloads not yet acked that are currently assigned to `fd`.
+    #[must_use]
+    pub fn in_flight_bytes_of(&self, fd: RawFd) -> usize {
+        self.payload_sizes
+            .iter()
+            .filter(|(seq, _)| self.sockets.payload_fd(**seq) == Some(fd))
+            .map(|(_, payload_size)| payload_size)
+            .sum()
     }
 
+    /// Payload sizes assigned to each socket on the most recent `send`.
+    #[must_use]
+    pub fn last_apportionment(&self) -> &HashMap<RawFd, usize> {
+        &self.last_apportionment
+    }
+
+    /// Also return the payload size left unassigned, e.g. when there are no sockets.
     fn assign_send(
         &self,
         payload_size: usize,
-    ) -> (Vec<RawFd>, Vec<(RawFd, usize, time::Duration)>) {
-        let mut payload_size_left = payload_size;
+    ) -> (Vec<RawFd>, Vec<(RawFd, usize, time::Duration)>, usize) {
+        let shares = self.apportion(payload_size);
+        let payload_size_left = payload_size - shares.values().sum::<usize>();
+
+        // Assign sequences in a reproducible order
+        let mut sockets = self.sockets.sockets().iter().collect::<Vec<_>>();
+        sockets.sort_unstable_by_key(|(fd, _)| **fd);
+
         let mut pings = Vec::new();
         let mut payloads = Vec::new();
-        for (&fd, socket) in self.sockets.sockets() {
-            // Calculate payload size with ceiling
-            let weight = match self.scheduler.weight(&fd) {
-                Some(weight) => weight,
-                None => {
-                    // Even weight
-                    1.0 / self.sockets.sockets().len() as f64
-                }
-            };
-            let payload_size = payload_size as f64 * weight;
-            let payload_size = payload_size.ceil() as usize;
-
-            // Make sure not exceed payload size
-            let payload_size = usize::min(payload_size, payload_size_left);
-            payload_size_left -= payload_size;
+        for (&fd, socket) in sockets {
+            let payload_size = shares[&fd];
 
             // If no payload to send, then send a ping instead
             if payload_size == 0 {
@@ -112,42 +386,268 @@ impl Sender {
             }
 
             // Get timeout
-            let timeout = socket
-                .rtt()
-                .map(|rtt| rtt * 2)
-                .unwrap_or_else(|| self.default_rto);
+            let timeout = socket.rto().unwrap_or_else(|| self.fallback_rto());
 
             // Send payload
             payloads.push((fd, payload_size, timeout));
         }
-        assert_eq!(payload_size_left, 0);
 
-        (pings, payloads)
+        (pings, payloads, payload_size_left)
+    }
+
+    /// Split `payload_size` across sockets by weight.
+    ///
+    /// The shares sum up to `payload_size` if there is any socket.
+    fn apportion(&self, payload_size: usize) -> HashMap<RawFd, usize> {
+        let sockets = self.sockets.sockets();
+        let weights = sockets
+            .keys()
+            .map(|fd| match self.scheduler.weight(fd) {
+                Some(weight) => (*fd, weight),
+                None => {
+                    // Even weight
+                    (*fd, 1.0 / sockets.len() as f64)
+                }
+            })
+            .collect::<Vec<_>>();
+        largest_remainder(payload_size, &weights)
+    }
+
+    /// A new RTT sample, from a payload ack or a ping ack alike, updates the scheduler; see `SendConfig::scheduler_update_interval`.
+    ///
+    /// So sockets that only carry pings still earn their weights.
+    pub fn ack(&mut self, now: T, fd: RawFd, seq: Seq16, space: AckSpace) {
+        let rtt = self.ack_inner(now, fd, seq, space);
+
+        // Let the scheduler track the new RTT sample
+        if rtt.is_some() {
+            let due = match self.last_scheduler_update {
+                Some(last) => now.saturating_elapsed(last) >= self.scheduler_update_interval,
+                None => true,
+            };
+            if due {
+                self.last_scheduler_update = Some(now);
+                self.update_scheduler();
+            }
+        }
     }
 
-    pub fn ack(&mut self, now: time::Instant, fd: RawFd, seq: Seq16, space: AckSpace) {
+    /// Same as `ack` but leave the scheduler to the caller, so a batch of acks updates it once.
+    ///
+    /// Return the new RTT sample, if any.
+    fn ack_inner(
+        &mut self,
+        now: T,
+        fd: RawFd,
+        seq: Seq16,
+        space: AckSpace,
+    ) -> Option<time::Duration> {
+        self.metrics.acks_received += 1;
+        self.last_ack = Some(now);
+
         // Ack the payload in `payload_queue`
+        if let AckSpace::Payload | AckSpace::Both = space {
+            self.orphaned.remove(&seq);
+            if let Some(payload_size) = self.payload_sizes.remove(&seq) {
+                // Credit the delivery to the socket the payload was assigned to
+                if let Some(assigned_fd) = self.sockets.payload_fd(seq) {
+                    self.sockets.record_delivery(assigned_fd, payload_size, now);
+                }
+            }
+        }
         let space = match space {
             AckSpace::Payload => {
                 let rtt = self.payload_queue.ack(seq, now, fd);
-                sockets::AckSpace::Payload { rtt }
+                sockets::AckSpace::Payload { rtt, now }
             }
             AckSpace::Ping => sockets::AckSpace::Ping { now },
+            AckSpace::Both => {
+                let rtt = self.payload_queue.ack(seq, now, fd);
+                sockets::AckSpace::Both { rtt, now }
+            }
         };
 
         // Ack the socket-related data
-        self.sockets.ack(fd, seq, space);
+        let rtt = self.sockets.ack(fd, seq, space);
+
+        self.notify_credit_transitions();
+
+        rtt
+    }
+
+    /// Apply a batch of acks and update the scheduler with the RTT samples of all of them, regardless of `scheduler_update_interval`.
+    pub fn ack_many(&mut self, now: T, acks: &[(RawFd, Seq16, AckSpace)]) {
+        if acks.is_empty() {
+            return;
+        }
+        for &(fd, seq, space) in acks {
+            let _ = self.ack_inner(now, fd, seq, space);
+        }
+        self.last_scheduler_update = Some(now);
+        self.update_scheduler();
+    }
+
+    /// Ack `frame` received on `fd` in the space its type belongs to.
+    ///
+    /// `PayloadAck`, `PayloadAckEcn`, and `Ack` ack payloads and `PingAck` acks a ping.
+    /// Return the space acked, or `None` if `frame` is not an ack and is left untouched.
+    pub fn handle_ack_frame(&mut self, now: T, fd: RawFd, frame: &Frame) -> Option<AckSpace> {
+        let (seq, space) = match frame {
+            Frame::PayloadAck(payload_ack) => (payload_ack.seq, AckSpace::Payload),
+            Frame::PayloadAckEcn(payload_ack_ecn) => (payload_ack_ecn.seq, AckSpace::Payload),
+            Frame::PingAck(ping_ack) => (ping_ack.seq, AckSpace::Ping),
+            Frame::Ack(ack) => {
+                let _ = self.apply_ack_frame(now, fd, ack);
+                return Some(AckSpace::Payload);
+            }
+            _ => return None,
+        };
+        self.ack(now, fd, seq, space);
+        Some(space)
+    }
+
+    /// Ack the outstanding payloads below `ack.cumulative` first and then those in `ack.ranges`.
+    ///
+    /// Return the payloads acked by this call in the order they are acked.
+    pub fn apply_ack_frame(&mut self, now: T, fd: RawFd, ack: &Ack) -> Vec<Seq16> {
+        let mut acked = self
+            .payload_queue
+            .pending_sequences()
+            .filter(|seq| seq.is_before(ack.cumulative))
+            .collect::<Vec<_>>();
+        for seq in &acked {
+            let _ = self.ack_inner(now, fd, *seq, AckSpace::Payload);
+        }
+
+        // Then the holes filled beyond the cumulative point
+        for &(start, end) in &ack.ranges {
+            for seq in seq_range(start, end) {
+                if self.payload_queue.rtt_stopwatch(seq).is_none() {
+                    // Not outstanding
+                    continue;
+                }
+                let _ = self.ack_inner(now, fd, seq, AckSpace::Payload);
+                acked.push(seq);
+            }
+        }
+
+        if !acked.is_empty() {
+            self.last_scheduler_update = Some(now);
+            self.update_scheduler();
+        }
+        acked
+    }
+
+    /// Ack every outstanding payload strictly below `up_to`.
+    ///
+    /// The payloads are acked in ascending order, so the RTT of each socket ends up with its newest sample.
+    pub fn advance_cumulative_ack(&mut self, now: T, fd: RawFd, up_to: Seq16) {
+        let acked = self
+            .payload_queue
+            .pending_sequences()
+            .filter(|seq| seq.is_before(up_to))
+            .collect::<Vec<_>>();
+        if acked.is_empty() {
+            return;
+        }
+        for seq in acked {
+            let _ = self.ack_inner(now, fd, seq, AckSpace::Payload);
+        }
+
+        // Update scheduler
+        self.last_scheduler_update = Some(now);
+        self.update_scheduler();
+    }
+
+    /// Return the sockets that have been acked but not for `idle_for`.
+    ///
+    /// Sockets that have never been acked are not reported.
+    #[must_use]
+    pub fn idle_sockets(&self, now: T, idle_for: time::Duration) -> Vec<RawFd> {
+        let mut fds = self
+            .sockets
+            .sockets()
+            .iter()
+            .filter(|(_, socket)| {
+                socket
+                    .last_ack()
+                    .is_some_and(|last_ack| now.saturating_elapsed(last_ack) >= idle_for)
+            })
+            .map(|(fd, _)| *fd)
+            .collect::<Vec<_>>();
+        fds.sort_unstable();
+        fds
+    }
+
+    /// Return the sockets on which nothing has been sent for `interval`, so the caller can ping exactly those to keep them alive.
+    ///
+    /// Sockets that have never been sent on are due as well.
+    #[must_use]
+    pub fn due_pings(&self, now: T, interval: time::Duration) -> Vec<RawFd> {
+        let mut fds = self
+            .sockets
+            .sockets()
+            .iter()
+            .filter(|(_, socket)| match socket.last_send() {
+                Some(last_send) => now.saturating_elapsed(last_send) >= interval,
+                None => true,
+            })
+            .map(|(fd, _)| *fd)
+            .collect::<Vec<_>>();
+        fds.sort_unstable();
+        fds
+    }
+
+    /// Retransmit the most recent unacked payload if neither a payload has been sent nor an ack has been received for `2 * RTT`.
+    ///
+    /// This recovers tail losses that leave no later acks to trigger a fast retransmit.
+    #[must_use]
+    pub fn tail_loss_probe(&mut self, now: T) -> Vec<SendFrame> {
+        if !self.tail_loss_probe {
+            return Vec::new();
+        }
+
+        // Get the most recent unacked payload
+        let Some(seq) = self.payload_queue.newest_unacked() else {
+            return Vec::new();
+        };
+        let Some(fd) = self.sockets.payload_fd(seq) else {
+            return Vec::new();
+        };
+
+        // Get probe timeout
+        let probe_timeout = self.sockets.sockets()[&fd]
+            .rto()
+            .unwrap_or_else(|| self.fallback_rto());
+
+        // Wait for new data and acks
+        for instant in [self.last_send, self.last_ack].into_iter().flatten() {
+            if now.saturating_elapsed(instant) < probe_timeout {
+                return Vec::new();
+            }
+        }
+
+        self.last_send = Some(now);
+        vec![SendFrame::Retransmit(RetransmitSendFrame { fd, seq })]
     }
 
     /// Ignoring the error does not cause data loss.
+    ///
+    /// Payloads that no credible socket has room for are left to the next call; see `set_socket_window`.
     #[must_use]
     pub fn retransmit_rto_payloads(
         &mut self,
-        now: time::Instant,
+        now: T,
     ) -> Result<RetransmitPayloads, ReassignPayloadError> {
         // Reassign RTO payloads to other credible sockets
         let vec = self.payload_queue.collect_timeout_sequences(now);
-        let res = self.sockets.reassign_rto_payloads(&vec);
+        let res = self
+            .sockets
+            .reassign_rto_payloads(&vec, self.scheduler.weights())
+            .map(|reassignment| reassignment.assigned);
+        self.metrics.rtos += vec.len();
+        self.count_reassignments(&res);
+        self.notify_credit_transitions();
 
         // Update scheduler
         self.update_scheduler();
@@ -155,33 +655,588 @@ impl Sender {
         res
     }
 
+    /// Sweep the RTO payloads, reassign them, and build the retransmit frames with the data from `fetch`.
+    ///
+    /// Payloads for which `fetch` returns `None` are skipped, e.g. if their data is already freed.
+    /// The reassigned payloads start timing out again from `now` on their new sockets, so a skipped payload does not discredit its new socket on the next tick.
+    /// While no socket is credible, the payloads are retransmitted on the sockets they are assigned to, so a loss on every socket does not stall them.
+    #[must_use]
+    pub fn on_timeout_tick(
+        &mut self,
+        now: T,
+        mut fetch: impl FnMut(Seq16) -> Option<Vec<u8>>,
+    ) -> Vec<(RawFd, Frame)> {
+        let retx = match self.retransmit_rto_payloads(now) {
+            Ok(retx) => retx,
+            Err(ReassignPayloadError::NoSocketsLeft { payloads }) => payloads
+                .into_iter()
+                .filter_map(|seq| Some((self.sockets.payload_fd(seq)?, seq)))
+                .collect(),
+        };
+
+        let mut frames = Vec::new();
+        for (fd, seq) in retx {
+            // Restart the stopwatch
+            let timeout = self.sockets.sockets()[&fd]
+                .rto()
+                .unwrap_or_else(|| self.fallback_rto());
+            let _ = self.payload_queue.retransmit(seq, now, timeout, fd);
+
+            let Some(data) = fetch(seq) else {
+                continue;
+            };
+            let payload = Payload {
+                seq,
+                data,
+                retransmit: true,
+            };
+            frames.push((fd, Frame::Payload(payload)));
+        }
+        frames
+    }
+
+    /// Take effect on the next scheduler update, e.g. on the next RTO or change of sockets.
+    pub fn set_learning_rate(&mut self, learning_rate: f64) -> Result<(), SendConfigError> {
+        if !(0.0 < learning_rate && learning_rate < 1.0) {
+            return Err(SendConfigError::LearningRateOutOfRange);
+        }
+        self.scheduler.set_learning_rate(learning_rate);
+        self.config.learning_rate = learning_rate;
+        Ok(())
+    }
+
+    /// Take effect on payloads sent from now on to sockets without an RTT sample, as long as no socket has one; see `fallback_rto`.
+    pub fn set_default_rto(&mut self, default_rto: time::Duration) -> Result<(), SendConfigError> {
+        if default_rto.is_zero() {
+            return Err(SendConfigError::ZeroDefaultRto);
+        }
+        self.default_rto = default_rto;
+        self.config.default_rto = default_rto;
+        Ok(())
+    }
+
+    /// Return the sockets with a ping that is sent but not yet acked.
+    #[must_use]
+    pub fn pending_pings(&self) -> Vec<RawFd> {
+        let mut fds = self
+            .sockets
+            .sockets()
+            .iter()
+            .filter(|(_, socket)| socket.has_pending_ping())
+            .map(|(fd, _)| *fd)
+            .collect::<Vec<_>>();
+        fds.sort_unstable();
+        fds
+    }
+
+    /// Count a duplicate payload ack for the cumulative point `seq`.
+    ///
+    /// Return the fast-retransmitted payload once `dup_ack_threshold` duplicates have been counted.
+    #[must_use]
+    pub fn on_dup_ack(&mut self, fd: RawFd, seq: Seq16) -> Option<RetransmitPayloads> {
+        if !self.sockets.sockets().contains_key(&fd) {
+            // Socket was already removed
+            return None;
+        }
+
+        // Reset the counter when the cumulative point advances
+        let count = match &mut self.dup_acks {
+            Some(dup_acks) if seq.is_before(dup_acks.seq) => {
+                // Stale cumulative point
+                return None;
+            }
+            Some(dup_acks) if seq == dup_acks.seq => {
+                dup_acks.count += 1;
+                dup_acks.count
+            }
+            _ => {
+                self.dup_acks = Some(DupAcks { seq, count: 1 });
+                1
+            }
+        };
+        if count != self.dup_ack_threshold {
+            return None;
+        }
+
+        self.fast_retransmit(seq)
+    }
+
+    /// Immediately reassign a payload that the receiver has declared lost.
+    #[must_use]
+    pub fn nack(&mut self, fd: RawFd, seq: Seq16) -> Option<RetransmitPayloads> {
+        if !self.sockets.sockets().contains_key(&fd) {
+            // Socket was already removed
+            return None;
+        }
+
+        self.fast_retransmit(seq)
+    }
+
+    #[must_use]
+    fn fast_retransmit(&mut self, seq: Seq16) -> Option<RetransmitPayloads> {
+        // Skip the payload if it was already acked
+        self.sockets.payload_fd(seq)?;
+
+        // Discredit the assigned socket and reassign the payload to a credible one
+        let res = self
+            .sockets
+            .reassign_rto_payloads(&[seq], self.scheduler.weights())
+            .map(|reassignment| reassignment.assigned);
+        self.count_reassignments(&res);
+        self.notify_credit_transitions();
+
+        // Update scheduler
+        self.update_scheduler();
+
+        // Without credible sockets, the payload is left to the RTO
+        res.ok()
+    }
+
+    /// Replace the weigher set up from `SendConfig::scheduler_input`.
+    ///
+    /// Sockets with bad credit are never weighed.
+    pub fn set_weigher(&mut self, weigher: impl Weigher<T> + 'static) {
+        self.weigher = Box::new(weigher);
+    }
+
+    /// Cap the payloads a credible socket takes on RTO reassignment at `socket_window`.
+    ///
+    /// `None` by default, which leaves sockets unbounded.
+    pub fn set_socket_window(&mut self, socket_window: Option<usize>) {
+        self.sockets.set_socket_window(socket_window);
+    }
+
+    /// Suggest a `payload_queue_size` that keeps all credible sockets busy at `target_bps`.
+    ///
+    /// Each credible socket contributes its bandwidth-delay product in units of `mss` bytes.
+    /// The suggestion saturates at `MAX_WINDOW`, the largest `payload_queue_size` the config accepts.
+    ///
+    /// Return `0` if `mss` is `0`.
+    #[must_use]
+    pub fn recommended_window(&self, target_bps: u64, mss: usize) -> usize {
+        if mss == 0 {
+            return 0;
+        }
+        let mut window: usize = 0;
+        for socket in self.sockets.sockets().values() {
+            if socket.credit() == Credit::Bad {
+                continue;
+            }
+            let Some(rtt) = socket.rtt() else {
+                continue;
+            };
+            let bdp = target_bps as u128 / 8 * rtt.as_nanos() / 1_000_000_000;
+            let payloads = usize::try_from(bdp).unwrap_or(usize::MAX).div_ceil(mss);
+            window = window.saturating_add(payloads);
+        }
+        usize::min(window, MAX_WINDOW)
+    }
+
+    #[must_use]
+    pub fn socket_count(&self) -> usize {
+        self.sockets.sockets().len()
+    }
+
+    /// The managed sockets in ascending order
+    #[must_use]
+    pub fn fds(&self) -> Vec<RawFd> {
+        let mut fds = self.sockets.sockets().keys().copied().collect::<Vec<_>>();
+        fds.sort_unstable();
+        fds
+    }
+
+    /// The RTT of the connection: the RTTs of the sockets averaged by their scheduler weights, so the fast, heavily used sockets dominate.
+    ///
+    /// The RTTs are averaged evenly if no socket with an RTT sample has a positive weight.
+    /// `None` until a socket has an RTT sample.
+    #[must_use]
+    pub fn aggregate_rtt(&self) -> Option<time::Duration> {
+        let rtts = self
+            .sockets
+            .sockets()
+            .iter()
+            .filter_map(|(fd, socket)| {
+                let rtt = socket.rtt()?;
+                let weight = self.scheduler.weight(fd).unwrap_or(0.0).max(0.0);
+                Some((rtt, weight))
+            })
+            .collect::<Vec<_>>();
+        if rtts.is_empty() {
+            return None;
+        }
+
+        let total_weight = rtts.iter().map(|(_, weight)| weight).sum::<f64>();
+        if total_weight <= 0.0 {
+            let total = rtts.iter().map(|(rtt, _)| *rtt).sum::<time::Duration>();
+            return Some(total / rtts.len() as u32);
+        }
+        let blended = rtts
+            .iter()
+            .map(|(rtt, weight)| rtt.as_secs_f64() * weight)
+            .sum::<f64>();
+        Some(time::Duration::from_secs_f64(blended / total_weight))
+    }
+
+    /// The RTO of sockets without an RTT sample: twice `aggregate_rtt`, or the default RTO until a socket has an RTT sample
+    fn fallback_rto(&self) -> time::Duration {
+        self.aggregate_rtt()
+            .map(|rtt| rtt * 2)
+            .unwrap_or(self.default_rto)
+    }
+
+    /// The scheduler weight of `fd`, or `None` if `fd` is unknown or the scheduler has not weighted any socket yet
+    #[must_use]
+    pub fn weight_of(&self, fd: RawFd) -> Option<f64> {
+        if !self.sockets.sockets().contains_key(&fd) {
+            return None;
+        }
+        self.scheduler.weight(&fd)
+    }
+
+    /// The scheduler weight of each socket
+    #[must_use]
+    pub fn weights(&self) -> &HashMap<RawFd, f64> {
+        self.scheduler.weights()
+    }
+
+    #[must_use]
+    pub fn metrics(&self) -> SendMetrics {
+        let mut metrics = self.metrics.clone();
+        metrics.outstanding = self
+            .sockets
+            .sockets()
+            .iter()
+            .map(|(fd, socket)| (*fd, socket.payloads().len()))
+            .collect();
+        metrics
+    }
+
+    /// The fraction of payloads that have timed out on each socket since creation or `reset_loss_stats`.
+    #[must_use]
+    pub fn loss_rates(&self) -> HashMap<RawFd, f64> {
+        self.sockets
+            .sockets()
+            .iter()
+            .map(|(fd, socket)| (*fd, socket.loss_rate()))
+            .collect()
+    }
+
+    /// Start the loss rates over so they do not carry ancient history.
+    pub fn reset_loss_stats(&mut self) {
+        self.sockets.reset_stats();
+    }
+
+    /// Override the credit of `fd`.
+    ///
+    /// A `Bad` socket leaves the RTT learning set, so it is not assigned payloads until it earns back `Good` credit with an RTT sample.
+    pub fn set_credit(&mut self, fd: RawFd, credit: Credit) {
+        self.sockets.set_credit(fd, credit);
+        self.notify_credit_transitions();
+        self.update_scheduler();
+    }
+
+    /// Observe the credit transitions of sockets.
+    ///
+    /// The observer is only called when the credit of a socket actually changes.
+    pub fn set_credit_observer(&mut self, observer: impl FnMut(RawFd, Credit, Credit) + 'static) {
+        self.credit_observer = Some(Box::new(observer));
+    }
+
+    fn notify_credit_transitions(&mut self) {
+        let transitions = self.sockets.take_credit_transitions();
+
+        // A socket going bad is a strong signal that network conditions changed
+        let discredited = transitions
+            .iter()
+            .any(|(_, old, new)| *old == Credit::Good && *new == Credit::Bad);
+        if discredited && self.reset_weights_on_discredit {
+            self.scheduler.reset_to_even();
+        }
+
+        let Some(observer) = &mut self.credit_observer else {
+            return;
+        };
+        for (fd, old, new) in transitions {
+            observer(fd, old, new);
+        }
+    }
+
+    fn count_reassignments(&mut self, res: &Result<RetransmitPayloads, ReassignPayloadError>) {
+        if let Ok(retx) = res {
+            self.metrics.reassignments += retx.len();
+        }
+    }
+
     fn update_scheduler(&mut self) {
-        let mut rtts = HashMap::new();
+        let mut costs = HashMap::new();
         for (&fd, socket) in self.sockets.sockets() {
             if socket.credit() == Credit::Bad {
                 continue;
             }
-            if let Some(rtt) = socket.rtt() {
-                rtts.insert(fd, rtt.as_secs_f64());
+            if let Some(cost) = self.weigher.cost(fd, socket) {
+                costs.insert(fd, cost);
             }
         }
-        self.scheduler.update(&rtts);
+        self.scheduler.update(&costs);
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SendConfig {
     pub payload_queue_size: usize,
+    /// The RTO of sockets without an RTT sample until any socket has one; see `Sender::aggregate_rtt`
     pub default_rto: time::Duration,
     pub learning_rate: f64,
+    /// How `learning_rate` adapts to each socket
+    pub learning_rate_mode: LearningRateMode,
+    /// Number of duplicate acks that triggers a fast retransmit
+    pub dup_ack_threshold: usize,
+    /// The minimum weight of each credible socket
+    pub min_weight: f64,
+    /// Enable `Sender::tail_loss_probe`
+    pub tail_loss_probe: bool,
+    /// Seed the randomized scheduling to make tests and simulations reproducible
+    ///
+    /// `None` seeds from entropy.
+    pub rng_seed: Option<u64>,
+    /// Reset the weights to even once a socket loses its good credit, so they quickly recover from a collapse
+    pub reset_weights_on_discredit: bool,
+    /// The per-socket cost the scheduler minimizes
+    pub scheduler_input: SchedulerInput,
+    /// The minimum time between the scheduler updates triggered by `Sender::ack`
+    ///
+    /// Zero updates the scheduler on every new RTT sample.
+    pub scheduler_update_interval: time::Duration,
+    /// Consecutive RTT samples a `Bad` socket needs to become `Good`
+    pub credit_samples: usize,
+    /// Consecutive RTOs that turn a `Good` socket `Bad`
+    pub discredit_rtos: usize,
+    /// How each socket smooths its RTT samples and derives its RTO
+    pub rtt_smoothing: RttSmoothing,
+    /// The latest RTT samples each socket keeps for `Socket::rtt_percentile`
+    pub rtt_history: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum SendFrame {
-    Payload(PayloadSendFrame),
-    Ping(PingSendFrame),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SchedulerInput {
+    /// The smoothed RTT
+    Rtt,
+    /// The RTT scaled by `1 / (1 - loss rate)` to account for the cost of retransmissions
+    EffectiveRtt,
+    /// The inverse of the bytes acked per second since creation or `reset_loss_stats`
+    Bandwidth,
 }
 
+impl SendConfig {
+    /// Override the fields of `SendConfig::default` one by one.
+    #[must_use]
+    pub fn builder() -> SendConfigBuilder {
+        SendConfigBuilder {
+            config: Self::default(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SendConfigError> {
+        if self.payload_queue_size == 0 {
+            return Err(SendConfigError::ZeroPayloadQueueSize);
+        }
+        if self.payload_queue_size > MAX_WINDOW {
+            return Err(SendConfigError::PayloadQueueSizeTooLarge);
+        }
+        if self.default_rto.is_zero() {
+            return Err(SendConfigError::ZeroDefaultRto);
+        }
+        // Also rejects NaN
+        if !(0.0 < self.learning_rate && self.learning_rate < 1.0) {
+            return Err(SendConfigError::LearningRateOutOfRange);
+        }
+        if self.credit_samples == 0 || self.discredit_rtos == 0 {
+            return Err(SendConfigError::ZeroCreditHysteresis);
+        }
+        if let RttSmoothing::Ewma { alpha } = self.rtt_smoothing {
+            // Also rejects NaN
+            if !(0.0 < alpha && alpha <= 1.0) {
+                return Err(SendConfigError::EwmaAlphaOutOfRange);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            payload_queue_size: 1024,
+            default_rto: time::Duration::from_secs(1),
+            learning_rate: 0.1,
+            learning_rate_mode: LearningRateMode::Fixed,
+            dup_ack_threshold: 3,
+            min_weight: 0.0,
+            tail_loss_probe: false,
+            rng_seed: None,
+            reset_weights_on_discredit: false,
+            scheduler_input: SchedulerInput::Rtt,
+            scheduler_update_interval: time::Duration::ZERO,
+            credit_samples: 1,
+            discredit_rtos: 1,
+            rtt_smoothing: RttSmoothing::LatestSample,
+            rtt_history: DEFAULT_RTT_HISTORY,
+        }
+    }
+}
+
+/// See `SendConfig::builder`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SendConfigBuilder {
+    config: SendConfig,
+}
+
+impl SendConfigBuilder {
+    #[must_use]
+    pub fn payload_queue_size(mut self, payload_queue_size: usize) -> Self {
+        self.config.payload_queue_size = payload_queue_size;
+        self
+    }
+
+    #[must_use]
+    pub fn default_rto(mut self, default_rto: time::Duration) -> Self {
+        self.config.default_rto = default_rto;
+        self
+    }
+
+    #[must_use]
+    pub fn learning_rate(mut self, learning_rate: f64) -> Self {
+        self.config.learning_rate = learning_rate;
+        self
+    }
+
+    #[must_use]
+    pub fn learning_rate_mode(mut self, learning_rate_mode: LearningRateMode) -> Self {
+        self.config.learning_rate_mode = learning_rate_mode;
+        self
+    }
+
+    #[must_use]
+    pub fn dup_ack_threshold(mut self, dup_ack_threshold: usize) -> Self {
+        self.config.dup_ack_threshold = dup_ack_threshold;
+        self
+    }
+
+    #[must_use]
+    pub fn min_weight(mut self, min_weight: f64) -> Self {
+        self.config.min_weight = min_weight;
+        self
+    }
+
+    #[must_use]
+    pub fn tail_loss_probe(mut self, tail_loss_probe: bool) -> Self {
+        self.config.tail_loss_probe = tail_loss_probe;
+        self
+    }
+
+    #[must_use]
+    pub fn rng_seed(mut self, rng_seed: Option<u64>) -> Self {
+        self.config.rng_seed = rng_seed;
+        self
+    }
+
+    #[must_use]
+    pub fn reset_weights_on_discredit(mut self, reset_weights_on_discredit: bool) -> Self {
+        self.config.reset_weights_on_discredit = reset_weights_on_discredit;
+        self
+    }
+
+    #[must_use]
+    pub fn scheduler_input(mut self, scheduler_input: SchedulerInput) -> Self {
+        self.config.scheduler_input = scheduler_input;
+        self
+    }
+
+    #[must_use]
+    pub fn scheduler_update_interval(mut self, scheduler_update_interval: time::Duration) -> Self {
+        self.config.scheduler_update_interval = scheduler_update_interval;
+        self
+    }
+
+    #[must_use]
+    pub fn credit_samples(mut self, credit_samples: usize) -> Self {
+        self.config.credit_samples = credit_samples;
+        self
+    }
+
+    #[must_use]
+    pub fn discredit_rtos(mut self, discredit_rtos: usize) -> Self {
+        self.config.discredit_rtos = discredit_rtos;
+        self
+    }
+
+    #[must_use]
+    pub fn rtt_smoothing(mut self, rtt_smoothing: RttSmoothing) -> Self {
+        self.config.rtt_smoothing = rtt_smoothing;
+        self
+    }
+
+    #[must_use]
+    pub fn rtt_history(mut self, rtt_history: usize) -> Self {
+        self.config.rtt_history = rtt_history;
+        self
+    }
+
+    pub fn build(self) -> Result<SendConfig, SendConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SendConfigError {
+    #[error("payload queue size is zero")]
+    ZeroPayloadQueueSize,
+    #[error("payload queue size exceeds the max window")]
+    PayloadQueueSizeTooLarge,
+    #[error("default RTO is zero")]
+    ZeroDefaultRto,
+    #[error("learning rate is not in (0, 1)")]
+    LearningRateOutOfRange,
+    #[error("credit samples or discredit RTOs is zero")]
+    ZeroCreditHysteresis,
+    #[error("EWMA alpha is not in (0, 1]")]
+    EwmaAlphaOutOfRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SendMetrics {
+    pub payloads_sent: usize,
+    pub pings_sent: usize,
+    pub acks_received: usize,
+    /// Payloads that have timed out
+    pub rtos: usize,
+    /// Payloads that have been reassigned to other sockets
+    pub reassignments: usize,
+    /// Outstanding payloads of each socket
+    pub outstanding: HashMap<RawFd, usize>,
+}
+
+struct DupAcks {
+    seq: Seq16,
+    count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendFrame {
+    Payload(PayloadSendFrame),
+    Ping(PingSendFrame),
+    Retransmit(RetransmitSendFrame),
+}
+
+/// The bytes a payload frame takes on top of its data: the frame type, the sequence and the data size
+pub const PAYLOAD_FRAME_OVERHEAD: usize = 1 + 2 + 2;
+
+/// The bytes a ping frame takes: the frame type and the sequence
+pub const PING_FRAME_LEN: usize = 1 + 2;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PayloadSendFrame {
     pub fd: RawFd,
@@ -195,22 +1250,69 @@ pub struct PingSendFrame {
     pub seq: Seq16,
 }
 
+/// Resend the data of an outstanding payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetransmitSendFrame {
+    pub fd: RawFd,
+    pub seq: Seq16,
+}
+
+/// Hamilton apportionment of `units` in proportion to `weights`.
+///
+/// Each key gets the floor of its quota, and the units left go one each to the keys with the largest fractional parts, ties broken by the smaller key.
+/// Keys are weighted evenly if no weight is positive.
+fn largest_remainder(units: usize, weights: &[(RawFd, f64)]) -> HashMap<RawFd, usize> {
+    let total = weights
+        .iter()
+        .map(|(_, weight)| weight.max(0.0))
+        .sum::<f64>();
+    let quota = |weight: f64| match total > 0.0 {
+        true => units as f64 * weight.max(0.0) / total,
+        false => units as f64 / weights.len() as f64,
+    };
+
+    // Floor of each quota
+    let mut shares = HashMap::new();
+    let mut remainders = Vec::new();
+    for &(fd, weight) in weights {
+        let quota = quota(weight);
+        let share = quota.floor() as usize;
+        shares.insert(fd, share);
+        remainders.push((fd, quota - share as f64));
+    }
+
+    // Hand out the units left by the largest fractional part
+    let units_left = units.saturating_sub(shares.values().sum());
+    remainders.sort_by(|(a_fd, a), (b_fd, b)| b.total_cmp(a).then(a_fd.cmp(b_fd)));
+    for (fd, _) in remainders.into_iter().take(units_left) {
+        *shares.get_mut(&fd).unwrap() += 1;
+    }
+
+    shares
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AckSpace {
     Payload,
     Ping,
+    /// A payload ack and a ping ack piggybacked for the same seq
+    Both,
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
     use super::*;
 
     #[test]
     fn ok() {
         let config = SendConfig {
             payload_queue_size: 100,
-            default_rto: time::Duration::from_secs(1),
-            learning_rate: 0.1,
+            ..SendConfig::default()
         };
         let mut send = Sender::new(config);
 
@@ -268,8 +1370,7 @@ mod tests {
     fn rto_no_rtt() {
         let config = SendConfig {
             payload_queue_size: 100,
-            default_rto: time::Duration::from_secs(1),
-            learning_rate: 0.1,
+            ..SendConfig::default()
         };
         let mut send = Sender::new(config);
 
@@ -328,8 +1429,7 @@ mod tests {
     fn rto_ok() {
         let config = SendConfig {
             payload_queue_size: 100,
-            default_rto: time::Duration::from_secs(1),
-            learning_rate: 0.1,
+            ..SendConfig::default()
         };
         let mut send = Sender::new(config);
 
@@ -389,8 +1489,7 @@ mod tests {
     fn ping_ok() {
         let config = SendConfig {
             payload_queue_size: 100,
-            default_rto: time::Duration::from_secs(1),
-            learning_rate: 0.1,
+            ..SendConfig::default()
         };
         let mut send = Sender::new(config);
 
@@ -448,14 +1547,1930 @@ mod tests {
     fn empty() {
         let config = SendConfig {
             payload_queue_size: 100,
-            default_rto: time::Duration::from_secs(1),
-            learning_rate: 0.1,
+            ..SendConfig::default()
         };
-        let mut send = Sender::new(config);
+        let mut send: Sender = Sender::new(config);
 
         let fd1 = 1;
 
         send.add_fd(fd1);
         send.remove_fd(fd1).unwrap();
     }
+
+    #[test]
+    fn last_apportionment() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        send.add_fd(fd3);
+
+        let now = time::Instant::now();
+
+        // Skew the weights
+        let frames = send.send(now, 3);
+        let now = now + time::Duration::from_millis(100);
+        for frame in frames {
+            match frame {
+                SendFrame::Payload(frame) if frame.fd == fd1 => {
+                    send.ack(now, frame.fd, frame.seq, AckSpace::Payload);
+                }
+                SendFrame::Payload(_) => (),
+                _ => unreachable!(),
+            }
+        }
+        let now = now + time::Duration::from_millis(100);
+        let _ = send.retransmit_rto_payloads(now);
+
+        let _ = send.send(now, 10);
+        let apportionment = send.last_apportionment();
+        assert_eq!(apportionment.values().sum::<usize>(), 10);
+        assert_eq!(apportionment[&fd1], 10);
+    }
+
+    #[test]
+    fn fast_retransmit() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        let now = time::Instant::now();
+
+        // Send 1 payload per socket
+        let frames = send.send(now, 2);
+        assert_eq!(frames.len(), 2);
+        let frames = frames
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => frame,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let acked = frames.iter().find(|frame| frame.fd == fd1).unwrap();
+        let lost = frames.iter().find(|frame| frame.fd == fd2).unwrap();
+
+        let now = now + time::Duration::from_millis(100);
+
+        // Ack the payload on fd1
+        send.ack(now, fd1, acked.seq, AckSpace::Payload);
+
+        // Duplicate acks
+        assert_eq!(send.on_dup_ack(fd1, lost.seq), None);
+        assert_eq!(send.on_dup_ack(fd1, lost.seq), None);
+        assert_eq!(send.on_dup_ack(fd1, lost.seq), Some(vec![(fd1, lost.seq)]));
+        assert_eq!(send.on_dup_ack(fd1, lost.seq), None);
+
+        // Counter resets when the cumulative point advances
+        let next = lost.seq.add(1);
+        assert_eq!(send.on_dup_ack(fd1, next), None);
+        assert_eq!(send.on_dup_ack(fd1, next), None);
+        assert_eq!(send.on_dup_ack(fd1, lost.seq), None);
+    }
+
+    #[test]
+    fn recommended_window() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        send.add_fd(fd3);
+
+        let now = time::Instant::now();
+
+        // Send 1 payload per socket
+        let frames = send.send(now, 3);
+        assert_eq!(frames.len(), 3);
+
+        // Ack fd1 after 100ms and fd2 after 200ms; fd3 is never credible
+        for frame in frames {
+            match frame {
+                SendFrame::Payload(frame) if frame.fd == fd1 => {
+                    let now = now + time::Duration::from_millis(100);
+                    send.ack(now, frame.fd, frame.seq, AckSpace::Payload);
+                }
+                SendFrame::Payload(frame) if frame.fd == fd2 => {
+                    let now = now + time::Duration::from_millis(200);
+                    send.ack(now, frame.fd, frame.seq, AckSpace::Payload);
+                }
+                SendFrame::Payload(_) => (),
+                _ => unreachable!(),
+            }
+        }
+
+        // 1 MB/s: 100 KB in flight on fd1 and 200 KB in flight on fd2
+        assert_eq!(send.recommended_window(8_000_000, 1000), 300);
+
+        assert_eq!(send.recommended_window(8_000_000, 0), 0);
+        assert_eq!(send.recommended_window(u64::MAX, 1), MAX_WINDOW);
+    }
+
+    #[test]
+    fn reenqueue_orphaned() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        send.add_fd(fd1);
+
+        let now = time::Instant::now();
+
+        // Send 2 payloads
+        let frames = send.send(now, 1);
+        assert_eq!(frames.len(), 1);
+        let frames = send.send(now, 1);
+        assert_eq!(frames.len(), 1);
+
+        // Remove the last socket
+        let Err(ReassignPayloadError::NoSocketsLeft { payloads }) = send.remove_fd(fd1) else {
+            unreachable!();
+        };
+        assert_eq!(payloads.len(), 2);
+
+        // The orphaned payloads keep their send window slots
+        assert_eq!(send.payload_queue.len(), 2);
+
+        send.add_fd(fd2);
+        send.add_fd(fd3);
+
+        // New sockets are not credible yet
+        let payloads = payloads.into_iter().collect::<Vec<_>>();
+        let Err(ReassignPayloadError::NoSocketsLeft { payloads: left }) =
+            send.reenqueue_orphaned(now, payloads.clone())
+        else {
+            unreachable!();
+        };
+        assert_eq!(left.len(), 2);
+        assert!(send.sockets.payload_fd(payloads[0]).is_none());
+
+        // Reenqueue the orphaned payloads
+        send.set_credit(fd2, Credit::Good);
+        send.set_credit(fd3, Credit::Good);
+        let retx = send.reenqueue_orphaned(now, payloads).unwrap();
+        assert_eq!(retx.len(), 2);
+        assert!(retx.iter().any(|(fd, _)| *fd == fd2));
+        assert!(retx.iter().any(|(fd, _)| *fd == fd3));
+        for (fd, seq) in &retx {
+            assert_eq!(send.sockets.payload_fd(*seq), Some(*fd));
+        }
+
+        assert_eq!(send.payload_queue.len(), 2);
+
+        // The reenqueued payloads time out again
+        let now = now + time::Duration::from_secs(1);
+        assert_eq!(send.payload_queue.collect_timeout_sequences(now).len(), 2);
+    }
+
+    #[test]
+    fn remove_fd_keeps_timing() {
+        let config = SendConfig {
+            rng_seed: Some(0),
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        let fd1 = 1;
+        let fd2 = 2;
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        let now = time::Instant::now();
+        let seq = send
+            .send(now, 2)
+            .into_iter()
+            .find_map(|frame| match frame {
+                SendFrame::Payload(frame) if frame.fd == fd1 => Some(frame.seq),
+                _ => None,
+            })
+            .unwrap();
+
+        // The payload moves to the remaining socket and keeps timing out from its first send
+        let retx = send.remove_fd(fd1).unwrap();
+        assert_eq!(retx, vec![(fd2, seq)]);
+        send.check_rep();
+        let now = now + config.default_rto;
+        assert!(send
+            .payload_queue
+            .collect_timeout_sequences(now)
+            .contains(&seq));
+    }
+
+    #[test]
+    fn remove_last_fd() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+
+        let now = time::Instant::now();
+        let frames = send.send(now, 1);
+        assert_eq!(frames.len(), 1);
+
+        let Err(ReassignPayloadError::NoSocketsLeft { payloads }) = send.remove_fd(fd1) else {
+            unreachable!();
+        };
+        assert_eq!(payloads.len(), 1);
+
+        // No phantom RTOs
+        let now = now + time::Duration::from_secs(2);
+        assert_eq!(send.payload_queue.collect_timeout_sequences(now), vec![]);
+        let Err(ReassignPayloadError::NoSocketsLeft { payloads }) =
+            send.retransmit_rto_payloads(now)
+        else {
+            unreachable!();
+        };
+        assert!(payloads.is_empty());
+        assert_eq!(send.metrics().rtos, 0);
+    }
+
+    #[test]
+    fn nack() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        let now = time::Instant::now();
+
+        // Send 1 payload per socket
+        let frames = send.send(now, 2);
+        assert_eq!(frames.len(), 2);
+        let frames = frames
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => frame,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let acked = frames.iter().find(|frame| frame.fd == fd1).unwrap();
+        let lost = frames.iter().find(|frame| frame.fd == fd2).unwrap();
+
+        let now = now + time::Duration::from_millis(100);
+
+        // Ack the payload on fd1
+        send.ack(now, fd1, acked.seq, AckSpace::Payload);
+
+        // Nack the payload on fd2
+        assert_eq!(send.nack(fd1, lost.seq), Some(vec![(fd1, lost.seq)]));
+        assert_eq!(send.sockets.sockets()[&fd2].credit(), Credit::Bad);
+
+        // Acked payloads are not reassigned
+        assert_eq!(send.nack(fd1, acked.seq), None);
+    }
+
+    #[test]
+    fn metrics() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        let now = time::Instant::now();
+
+        // Send 1 payload per socket
+        let frames = send.send(now, 2);
+        assert_eq!(frames.len(), 2);
+        let frames = frames
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => frame,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let acked = frames.iter().find(|frame| frame.fd == fd1).unwrap();
+        let lost = frames.iter().find(|frame| frame.fd == fd2).unwrap();
+
+        let metrics = send.metrics();
+        assert_eq!(metrics.payloads_sent, 2);
+        assert_eq!(metrics.outstanding[&fd1], 1);
+        assert_eq!(metrics.outstanding[&fd2], 1);
+
+        let now = now + time::Duration::from_millis(100);
+
+        // Ack the payload on fd1
+        send.ack(now, fd1, acked.seq, AckSpace::Payload);
+
+        let now = now + config.default_rto;
+
+        // RTO
+        let retx = send.retransmit_rto_payloads(now).unwrap();
+        assert_eq!(retx, vec![(fd1, lost.seq)]);
+
+        let metrics = send.metrics();
+        assert_eq!(metrics.payloads_sent, 2);
+        assert_eq!(metrics.pings_sent, 0);
+        assert_eq!(metrics.acks_received, 1);
+        assert_eq!(metrics.rtos, 1);
+        assert_eq!(metrics.reassignments, 1);
+        assert_eq!(metrics.outstanding[&fd1], 1);
+        assert_eq!(metrics.outstanding[&fd2], 0);
+    }
+
+    #[test]
+    fn credit_observer() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let transitions = Rc::new(RefCell::new(Vec::new()));
+        {
+            let transitions = Rc::clone(&transitions);
+            send.set_credit_observer(move |fd, old, new| {
+                transitions.borrow_mut().push((fd, old, new));
+            });
+        }
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        let now = time::Instant::now();
+
+        // Send 1 payload per socket
+        let frames = send.send(now, 2);
+        assert_eq!(frames.len(), 2);
+        let frames = frames
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => frame,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let acked = frames.iter().find(|frame| frame.fd == fd1).unwrap();
+
+        let now = now + time::Duration::from_millis(100);
+
+        // Bad -> good
+        send.ack(now, fd1, acked.seq, AckSpace::Payload);
+        assert_eq!(
+            *transitions.borrow(),
+            vec![(fd1, Credit::Bad, Credit::Good)]
+        );
+
+        let now = now + config.default_rto;
+
+        // fd2 stays bad and its payload moves to fd1
+        let _ = send.retransmit_rto_payloads(now).unwrap();
+        assert_eq!(transitions.borrow().len(), 1);
+
+        // Good -> bad
+        let _ = send.retransmit_rto_payloads(now);
+        assert_eq!(
+            *transitions.borrow(),
+            vec![
+                (fd1, Credit::Bad, Credit::Good),
+                (fd1, Credit::Good, Credit::Bad)
+            ]
+        );
+    }
+
+    #[test]
+    fn tail_loss_probe() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            tail_loss_probe: true,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+
+        let now = time::Instant::now();
+        assert!(send.tail_loss_probe(now).is_empty());
+
+        // Sample an RTT of 100ms
+        let frames = send.send(now, 1);
+        let SendFrame::Payload(frame) = frames[0] else {
+            unreachable!();
+        };
+        let rtt = time::Duration::from_millis(100);
+        let now = now + rtt;
+        send.ack(now, fd1, frame.seq, AckSpace::Payload);
+
+        // Leave a single payload outstanding
+        let frames = send.send(now, 1);
+        let SendFrame::Payload(frame) = frames[0] else {
+            unreachable!();
+        };
+
+        // Probe after `2 * RTT`
+        assert!(send.tail_loss_probe(now + rtt).is_empty());
+        let now = now + rtt * 2;
+        assert_eq!(
+            send.tail_loss_probe(now),
+            vec![SendFrame::Retransmit(RetransmitSendFrame {
+                fd: fd1,
+                seq: frame.seq
+            })]
+        );
+
+        // The probe restarts the probe timeout
+        assert!(send.tail_loss_probe(now).is_empty());
+        assert_eq!(send.tail_loss_probe(now + rtt * 2).len(), 1);
+    }
+
+    #[test]
+    fn ack_both() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+
+        let now = time::Instant::now();
+
+        // Send 1 ping
+        let frames = send.send(now, 0);
+        let SendFrame::Ping(ping) = frames[0] else {
+            unreachable!();
+        };
+
+        // Send 1 payload with the same seq
+        let now = now + time::Duration::from_millis(50);
+        let frames = send.send(now, 1);
+        let SendFrame::Payload(payload) = frames[0] else {
+            unreachable!();
+        };
+        assert_eq!(ping.seq, payload.seq);
+
+        // Ack both in one call
+        let rtt = time::Duration::from_millis(100);
+        let now = now + rtt;
+        send.ack(now, fd1, payload.seq, AckSpace::Both);
+
+        let socket = &send.sockets.sockets()[&fd1];
+        assert_eq!(socket.credit(), Credit::Good);
+        assert_eq!(socket.rtt(), Some(rtt));
+        assert_eq!(send.metrics().acks_received, 1);
+        assert_eq!(send.metrics().outstanding[&fd1], 0);
+
+        // The ping is no longer outstanding
+        let frames = send.send(now, 0);
+        assert!(matches!(frames[0], SendFrame::Ping(_)));
+    }
+
+    #[test]
+    fn mock_clock() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut clock = MockClock::new();
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        // Sample an RTT on `fd2` only
+        let frames = send.send(clock.now(), 2);
+        let rtt = time::Duration::from_millis(100);
+        clock.advance(rtt);
+        let mut lost = None;
+        for frame in frames {
+            let SendFrame::Payload(frame) = frame else {
+                unreachable!();
+            };
+            match frame.fd == fd2 {
+                true => send.ack(clock.now(), frame.fd, frame.seq, AckSpace::Payload),
+                false => lost = Some(frame.seq),
+            }
+        }
+        assert_eq!(send.sockets.sockets()[&fd2].rtt(), Some(rtt));
+
+        // The lost payload times out exactly at the default RTO
+        let lost = lost.unwrap();
+        clock.advance(time::Duration::from_secs(1) - rtt - time::Duration::from_millis(1));
+        assert_eq!(send.retransmit_rto_payloads(clock.now()).unwrap(), vec![]);
+        clock.advance(time::Duration::from_millis(1));
+        assert_eq!(
+            send.retransmit_rto_payloads(clock.now()).unwrap(),
+            vec![(fd2, lost)]
+        );
+    }
+
+    #[test]
+    fn pending_pings() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+        assert!(send.pending_pings().is_empty());
+
+        // Ping the socket
+        let now = time::Instant::now();
+        let frames = send.send(now, 0);
+        assert!(matches!(frames[..], [SendFrame::Ping(_)]));
+        assert_eq!(send.pending_pings(), vec![fd1]);
+
+        // The ping queue holds one ping per socket
+        assert!(send.sockets.send_ping(fd1, now).is_none());
+
+        // A payload retires the ping
+        let frames = send.send(now, 1);
+        assert!(frames
+            .iter()
+            .any(|frame| matches!(frame, SendFrame::Payload(_))));
+        assert!(send.pending_pings().is_empty());
+    }
+
+    #[test]
+    fn advance_cumulative_ack() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+
+        // Send five payloads
+        let now = time::Instant::now();
+        let mut seqs = Vec::new();
+        for i in 0..5 {
+            let frames = send.send(now + time::Duration::from_millis(i * 10), 1);
+            let SendFrame::Payload(frame) = frames[0] else {
+                unreachable!();
+            };
+            seqs.push(frame.seq);
+        }
+
+        // Ack the first four at once
+        let now = now + time::Duration::from_millis(100);
+        send.advance_cumulative_ack(now, fd1, seqs[4]);
+        assert_eq!(send.metrics().outstanding[&fd1], 1);
+        assert_eq!(send.payload_queue.newest_unacked(), Some(seqs[4]));
+
+        // The RTT comes from the newest sample
+        assert_eq!(
+            send.sockets.sockets()[&fd1].rtt(),
+            Some(time::Duration::from_millis(70))
+        );
+    }
+
+    #[test]
+    fn idle_sockets() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut clock = MockClock::new();
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        // Ping both sockets and ack `fd1` only
+        let frames = send.send(clock.now(), 0);
+        assert_eq!(frames.len(), 2);
+        for frame in frames {
+            let SendFrame::Ping(frame) = frame else {
+                unreachable!();
+            };
+            if frame.fd == fd1 {
+                send.ack(clock.now(), frame.fd, frame.seq, AckSpace::Ping);
+            }
+        }
+
+        let idle_for = time::Duration::from_secs(5);
+        clock.advance(idle_for - time::Duration::from_millis(1));
+        assert!(send.idle_sockets(clock.now(), idle_for).is_empty());
+        clock.advance(time::Duration::from_millis(1));
+        assert_eq!(send.idle_sockets(clock.now(), idle_for), vec![fd1]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+        assert!(Sender::<time::Instant>::try_new(config).is_ok());
+
+        let invalid = SendConfig {
+            payload_queue_size: 0,
+            ..config
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(SendConfigError::ZeroPayloadQueueSize)
+        );
+
+        let invalid = SendConfig {
+            payload_queue_size: MAX_WINDOW + 1,
+            ..config
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(SendConfigError::PayloadQueueSizeTooLarge)
+        );
+
+        let invalid = SendConfig {
+            default_rto: time::Duration::ZERO,
+            ..config
+        };
+        assert_eq!(invalid.validate(), Err(SendConfigError::ZeroDefaultRto));
+
+        let invalid = SendConfig {
+            discredit_rtos: 0,
+            ..config
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(SendConfigError::ZeroCreditHysteresis)
+        );
+
+        let invalid = SendConfig {
+            rtt_smoothing: RttSmoothing::Ewma { alpha: 0.0 },
+            ..config
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(SendConfigError::EwmaAlphaOutOfRange)
+        );
+
+        for learning_rate in [-0.1, 0.0, 1.0, f64::NAN] {
+            let invalid = SendConfig {
+                learning_rate,
+                ..config
+            };
+            assert_eq!(
+                Sender::<time::Instant>::try_new(invalid).err(),
+                Some(SendConfigError::LearningRateOutOfRange)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid send config")]
+    fn new_invalid_config() {
+        let config = SendConfig {
+            payload_queue_size: 0,
+            ..SendConfig::default()
+        };
+        let _send: Sender = Sender::new(config);
+    }
+
+    #[test]
+    fn loss_rates() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        // Send four payloads on each socket
+        let now = time::Instant::now();
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            frames.extend(send.send(now, 2));
+        }
+        assert_eq!(frames.len(), 8);
+
+        // Lose the last two payloads of `fd1`
+        let now = now + time::Duration::from_millis(100);
+        let mut fd1_acked = 0;
+        for frame in frames {
+            let SendFrame::Payload(frame) = frame else {
+                unreachable!();
+            };
+            if frame.fd == fd1 {
+                if fd1_acked == 2 {
+                    continue;
+                }
+                fd1_acked += 1;
+            }
+            send.ack(now, frame.fd, frame.seq, AckSpace::Payload);
+        }
+        let now = now + time::Duration::from_secs(1);
+        assert_eq!(send.retransmit_rto_payloads(now).unwrap().len(), 2);
+
+        // Half of the payloads on `fd1` were lost
+        let loss_rates = send.loss_rates();
+        assert_eq!(loss_rates[&fd1], 0.5);
+        assert_eq!(loss_rates[&fd2], 0.0);
+
+        send.reset_loss_stats();
+        let loss_rates = send.loss_rates();
+        assert_eq!(loss_rates[&fd1], 0.0);
+        assert_eq!(loss_rates[&fd2], 0.0);
+    }
+
+    #[test]
+    fn send_with_backpressure() {
+        let config = SendConfig {
+            payload_queue_size: 1,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        // No sockets to send on
+        let now = time::Instant::now();
+        let (frames, unscheduled) = send.send_with_backpressure(now, 10);
+        assert!(frames.is_empty());
+        assert_eq!(unscheduled, 10);
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+
+        let (frames, unscheduled) = send.send_with_backpressure(now, 10);
+        let [SendFrame::Payload(frame)] = frames[..] else {
+            unreachable!();
+        };
+        assert_eq!(frame.payload_size, 10);
+        assert_eq!(unscheduled, 0);
+
+        // The payload queue is full
+        let (frames, unscheduled) = send.send_with_backpressure(now, 10);
+        assert!(frames.is_empty());
+        assert_eq!(unscheduled, 10);
+
+        // Acks free up the payload queue
+        send.ack(now, fd1, frame.seq, AckSpace::Payload);
+        let (frames, unscheduled) = send.send_with_backpressure(now, 10);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(unscheduled, 0);
+    }
+
+    #[test]
+    fn send_one_unit_to_three_sockets() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        send.add_fd(1);
+        send.add_fd(2);
+        send.add_fd(3);
+
+        let now = time::Instant::now();
+        let (frames, unscheduled) = send.send_with_backpressure(now, 1);
+        assert_eq!(unscheduled, 0);
+        let payloads = frames
+            .iter()
+            .filter_map(|frame| match frame {
+                SendFrame::Payload(frame) => Some(frame.payload_size),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(payloads, vec![1]);
+        assert_eq!(send.last_apportionment().values().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn rng_seed() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            rng_seed: Some(42),
+            ..SendConfig::default()
+        };
+        let now = time::Instant::now();
+        let reassign = || {
+            let mut send = Sender::new(config);
+            let mut choices = Vec::new();
+            for _ in 0..16 {
+                // Send a payload on `fd1` alone
+                send.add_fd(1);
+                let _ = send.send(now, 1);
+
+                // Reassign the payload to either of the evenly weighted sockets
+                send.add_fd(2);
+                send.add_fd(3);
+                let [(fd, _)] = send.remove_fd(1).unwrap()[..] else {
+                    unreachable!();
+                };
+                choices.push(fd);
+
+                let _ = send.remove_fd(2);
+                let _ = send.remove_fd(3);
+            }
+            choices
+        };
+        let choices = reassign();
+        assert!(choices.contains(&2));
+        assert!(choices.contains(&3));
+        assert_eq!(choices, reassign());
+    }
+
+    #[test]
+    fn live_tuning() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        for learning_rate in [-0.1, 0.0, 1.0, f64::NAN] {
+            assert_eq!(
+                send.set_learning_rate(learning_rate),
+                Err(SendConfigError::LearningRateOutOfRange)
+            );
+        }
+        assert_eq!(send.set_learning_rate(0.5), Ok(()));
+        assert_eq!(
+            send.set_default_rto(time::Duration::ZERO),
+            Err(SendConfigError::ZeroDefaultRto)
+        );
+        assert_eq!(
+            send.set_default_rto(time::Duration::from_millis(200)),
+            Ok(())
+        );
+
+        let fd1 = 1;
+
+        send.add_fd(fd1);
+
+        // The new default RTO applies to new payloads
+        let now = time::Instant::now();
+        let _ = send.send(now, 1);
+        let now = now + time::Duration::from_millis(200);
+        assert_eq!(send.payload_queue.collect_timeout_sequences(now).len(), 1);
+    }
+
+    #[test]
+    fn set_credit() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        // Both sockets earn good credit
+        let now = time::Instant::now();
+        let frames = send.send(now, 2);
+        let now = now + time::Duration::from_millis(100);
+        for frame in frames {
+            let SendFrame::Payload(frame) = frame else {
+                unreachable!();
+            };
+            send.ack(now, frame.fd, frame.seq, AckSpace::Payload);
+        }
+        assert_eq!(send.sockets.sockets()[&fd2].credit(), Credit::Good);
+
+        send.set_credit(fd2, Credit::Bad);
+        assert_eq!(send.sockets.sockets()[&fd2].credit(), Credit::Bad);
+
+        // `fd2` only gets a ping
+        let frames = send.send(now, 10);
+        for frame in frames {
+            match frame {
+                SendFrame::Payload(frame) => {
+                    assert_eq!(frame.fd, fd1);
+                    assert_eq!(frame.payload_size, 10);
+                }
+                SendFrame::Ping(frame) => assert_eq!(frame.fd, fd2),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn largest_remainder() {
+        let shares = super::largest_remainder(10, &[(1, 0.5), (2, 0.3), (3, 0.2)]);
+        assert_eq!([shares[&1], shares[&2], shares[&3]], [5, 3, 2]);
+
+        // One unit over three even sockets
+        let shares = super::largest_remainder(1, &[(3, 1.0), (1, 1.0), (2, 1.0)]);
+        assert_eq!([shares[&1], shares[&2], shares[&3]], [1, 0, 0]);
+
+        // The largest fractional parts take the units left
+        let shares = super::largest_remainder(10, &[(1, 0.34), (2, 0.33), (3, 0.33)]);
+        assert_eq!([shares[&1], shares[&2], shares[&3]], [4, 3, 3]);
+        let shares = super::largest_remainder(7, &[(1, 0.0), (2, 0.0)]);
+        assert_eq!([shares[&1], shares[&2]], [4, 3]);
+    }
+
+    #[test]
+    fn peer_window() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        send.add_fd(1);
+        send.add_fd(2);
+        send.add_fd(3);
+        send.set_peer_window(2);
+
+        let payloads = |frames: &[SendFrame]| {
+            frames
+                .iter()
+                .filter(|frame| matches!(frame, SendFrame::Payload(_)))
+                .count()
+        };
+
+        // Only two of the three sockets get a payload
+        let now = time::Instant::now();
+        let (frames, unscheduled) = send.send_with_backpressure(now, 30);
+        assert_eq!(payloads(&frames), 2);
+        assert_eq!(unscheduled, 10);
+
+        // The window is used up
+        let (frames, unscheduled) = send.send_with_backpressure(now, 30);
+        assert_eq!(payloads(&frames), 0);
+        assert_eq!(unscheduled, 30);
+
+        send.set_peer_window(8);
+        let (frames, unscheduled) = send.send_with_backpressure(now, 30);
+        assert_eq!(payloads(&frames), 3);
+        assert_eq!(unscheduled, 0);
+    }
+
+    #[test]
+    fn reset_weights_on_discredit() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            reset_weights_on_discredit: true,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        send.add_fd(fd3);
+
+        // `fd1` is ten times as fast as the others
+        let now = time::Instant::now();
+        let mut frames = send.send(now, 3);
+        frames.sort_by_key(|frame| match frame {
+            SendFrame::Payload(frame) => frame.fd,
+            _ => unreachable!(),
+        });
+        for frame in frames {
+            let SendFrame::Payload(frame) = frame else {
+                unreachable!();
+            };
+            let rtt = match frame.fd == fd1 {
+                true => time::Duration::from_millis(10),
+                false => time::Duration::from_millis(100),
+            };
+            send.ack(now + rtt, frame.fd, frame.seq, AckSpace::Payload);
+        }
+
+        // Collapse onto `fd1`
+        let now = now + time::Duration::from_millis(100);
+        for _ in 0..100 {
+            let _ = send.retransmit_rto_payloads(now);
+        }
+        assert!(send.scheduler.weight(&fd1).unwrap() > 0.99);
+
+        // The remaining sockets are rebalanced
+        send.set_credit(fd1, Credit::Bad);
+        assert_eq!(send.scheduler.weight(&fd1), Some(0.0));
+        let weight2 = send.scheduler.weight(&fd2).unwrap();
+        let weight3 = send.scheduler.weight(&fd3).unwrap();
+        assert!((weight2 - 0.5).abs() < 0.1);
+        assert!((weight3 - 0.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn ack_many() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        // Send three payloads on each socket
+        let now = time::Instant::now();
+        let mut acks = Vec::new();
+        for _ in 0..3 {
+            for frame in send.send(now, 2) {
+                let SendFrame::Payload(frame) = frame else {
+                    unreachable!();
+                };
+                acks.push((frame.fd, frame.seq, AckSpace::Payload));
+            }
+        }
+        assert_eq!(acks.len(), 6);
+        let weights = send.scheduler.weights().clone();
+
+        let now = now + time::Duration::from_millis(100);
+        send.ack_many(now, &acks);
+        assert_eq!(send.metrics().acks_received, 6);
+        for fd in [fd1, fd2] {
+            assert_eq!(send.sockets.sockets()[&fd].credit(), Credit::Good);
+            assert_eq!(
+                send.sockets.sockets()[&fd].rtt(),
+                Some(time::Duration::from_millis(100))
+            );
+        }
+
+        // The scheduler has learned from the batch
+        assert_ne!(*send.scheduler.weights(), weights);
+        let now = now + time::Duration::from_secs(1);
+        assert!(send.retransmit_rto_payloads(now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ack_batch_steps_scheduler_once() {
+        /// Count the scheduler updates by the times `fd` is weighed
+        struct CountingWeigher {
+            fd: RawFd,
+            steps: Rc<Cell<usize>>,
+        }
+        impl Weigher for CountingWeigher {
+            fn cost(&self, fd: RawFd, socket: &Socket) -> Option<f64> {
+                if fd == self.fd {
+                    self.steps.set(self.steps.get() + 1);
+                }
+                RttWeigher.cost(fd, socket)
+            }
+        }
+
+        let mut send = Sender::new(SendConfig::default());
+        let fd1 = 1;
+        let fd2 = 2;
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        send.set_credit(fd1, Credit::Good);
+        let steps = Rc::new(Cell::new(0));
+        send.set_weigher(CountingWeigher {
+            fd: fd1,
+            steps: Rc::clone(&steps),
+        });
+
+        // Send three payloads on each socket and return their acks
+        let send_acks = |send: &mut Sender, now: time::Instant| {
+            let mut acks = Vec::new();
+            for _ in 0..3 {
+                for frame in send.send(now, 2) {
+                    let SendFrame::Payload(frame) = frame else {
+                        unreachable!();
+                    };
+                    acks.push((frame.fd, frame.seq, AckSpace::Payload));
+                }
+            }
+            acks
+        };
+        let end = |acks: &[(RawFd, Seq16, AckSpace)]| {
+            let last = acks.iter().map(|(_, seq, _)| seq.value()).max().unwrap();
+            Seq16::new(last + 1)
+        };
+        let now = time::Instant::now();
+        let rtt = time::Duration::from_millis(100);
+
+        let acks = send_acks(&mut send, now);
+        assert_eq!(acks.len(), 6);
+        send.ack_many(now + rtt, &acks);
+        assert_eq!(steps.replace(0), 1);
+
+        let now = now + rtt;
+        let acks = send_acks(&mut send, now);
+        send.advance_cumulative_ack(now + rtt, fd1, end(&acks));
+        assert_eq!(steps.replace(0), 1);
+
+        let now = now + rtt;
+        let acks = send_acks(&mut send, now);
+        let ack = Ack {
+            cumulative: end(&acks),
+            ranges: Vec::new(),
+        };
+        assert_eq!(send.apply_ack_frame(now + rtt, fd1, &ack).len(), 6);
+        assert_eq!(steps.replace(0), 1);
+    }
+
+    #[test]
+    fn in_flight_bytes() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+
+        send.add_fd(fd1);
+
+        let now = time::Instant::now();
+        let mut seqs = Vec::new();
+        for payload_size in [100, 20, 3] {
+            for frame in send.send(now, payload_size) {
+                let SendFrame::Payload(frame) = frame else {
+                    unreachable!();
+                };
+                seqs.push(frame.seq);
+            }
+        }
+        assert_eq!(send.in_flight_bytes(), 123);
+        assert_eq!(send.in_flight_bytes_of(fd1), 123);
+
+        // Ping acks leave the payloads in flight
+        send.ack(now, fd1, seqs[0], AckSpace::Ping);
+        assert_eq!(send.in_flight_bytes(), 123);
+        send.ack(now, fd1, seqs[0], AckSpace::Payload);
+        assert_eq!(send.in_flight_bytes(), 23);
+
+        // The rest move to `fd2` on RTO
+        send.add_fd(fd2);
+        for frame in send.send(now, 0) {
+            if let SendFrame::Ping(frame) = frame {
+                if frame.fd == fd2 {
+                    send.ack(now, fd2, frame.seq, AckSpace::Ping);
+                }
+            }
+        }
+        let now = now + time::Duration::from_secs(1);
+        assert_eq!(send.retransmit_rto_payloads(now).unwrap().len(), 2);
+        assert_eq!(send.in_flight_bytes(), 23);
+        assert_eq!(send.in_flight_bytes_of(fd1), 0);
+        assert_eq!(send.in_flight_bytes_of(fd2), 23);
+
+        send.advance_cumulative_ack(now, fd2, seqs[2].add(1));
+        assert_eq!(send.in_flight_bytes(), 0);
+    }
+
+    #[test]
+    fn due_pings() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        let interval = time::Duration::from_secs(1);
+        let now = time::Instant::now();
+        assert_eq!(send.due_pings(now, interval), vec![fd1, fd2]);
+
+        // Send a payload on `fd1` and a ping on `fd2`
+        let frames = send.send(now, 1);
+        assert_eq!(frames.len(), 2);
+        assert!(send.due_pings(now, interval).is_empty());
+
+        // Only `fd1` sends since the ping on `fd2` is still outstanding
+        let now = now + interval;
+        assert_eq!(send.due_pings(now, interval), vec![fd1, fd2]);
+        let frames = send.send(now, 1);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(send.due_pings(now, interval), vec![fd2]);
+
+        // `fd3` has never been sent on
+        send.add_fd(fd3);
+        assert_eq!(send.due_pings(now, interval), vec![fd2, fd3]);
+    }
+
+    #[test]
+    fn socket_count() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send: Sender = Sender::new(config);
+        assert_eq!(send.socket_count(), 0);
+        assert!(send.fds().is_empty());
+
+        for fd in [3, 1, 2] {
+            send.add_fd(fd);
+        }
+        assert_eq!(send.socket_count(), 3);
+        assert_eq!(send.fds(), vec![1, 2, 3]);
+
+        send.remove_fd(2).unwrap();
+        assert_eq!(send.socket_count(), 2);
+        assert_eq!(send.fds(), vec![1, 3]);
+    }
+
+    #[test]
+    fn effective_rtt() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            scheduler_input: SchedulerInput::EffectiveRtt,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let clean = 1;
+        let lossy = 2;
+        let rtt = time::Duration::from_millis(10);
+
+        send.add_fd(clean);
+        send.add_fd(lossy);
+        let mut now = time::Instant::now();
+
+        // Both sockets get the same RTT
+        let pings = send.send(now, 0);
+        now += rtt;
+        let acks = pings
+            .iter()
+            .map(|frame| {
+                let SendFrame::Ping(frame) = frame else {
+                    unreachable!();
+                };
+                (frame.fd, frame.seq, AckSpace::Ping)
+            })
+            .collect::<Vec<_>>();
+        send.ack_many(now, &acks);
+        send.scheduler.reset_to_even();
+
+        // One payload each, all acked, then one payload each where `lossy` loses its payload
+        for lose in [false, true] {
+            let frames = send.send(now, 10);
+            assert_eq!(frames.len(), 2);
+            now += rtt;
+            let acks = frames
+                .iter()
+                .filter_map(|frame| {
+                    let SendFrame::Payload(frame) = frame else {
+                        unreachable!();
+                    };
+                    (!lose || frame.fd == clean).then_some((frame.fd, frame.seq, AckSpace::Payload))
+                })
+                .collect::<Vec<_>>();
+            send.ack_many(now, &acks);
+        }
+        now += rtt;
+        let retx = send.retransmit_rto_payloads(now).unwrap();
+        send.ack_many(now, &[(clean, retx[0].1, AckSpace::Payload)]);
+        assert_eq!(send.loss_rates()[&lossy], 0.5);
+        assert_eq!(send.loss_rates()[&clean], 0.0);
+
+        // `lossy` earns back its credit with the same RTT as `clean`
+        for i in 0..10 {
+            if i == 1 {
+                assert_eq!(send.sockets.sockets()[&lossy].credit(), Credit::Good);
+
+                // Start from even weights so only the costs tell the sockets apart
+                send.scheduler.reset_to_even();
+            }
+            let pings = send.send(now, 0);
+            now += rtt;
+            let acks = pings
+                .iter()
+                .map(|frame| {
+                    let SendFrame::Ping(frame) = frame else {
+                        unreachable!();
+                    };
+                    (frame.fd, frame.seq, AckSpace::Ping)
+                })
+                .collect::<Vec<_>>();
+            send.ack_many(now, &acks);
+        }
+        assert_eq!(send.sockets.sockets()[&lossy].credit(), Credit::Good);
+        assert_eq!(
+            send.sockets.sockets()[&lossy].rtt(),
+            send.sockets.sockets()[&clean].rtt()
+        );
+        assert!(send.weights()[&clean] > send.weights()[&lossy]);
+    }
+
+    #[test]
+    fn send_within() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        send.add_fd(1);
+        send.add_fd(2);
+        let now = time::Instant::now();
+        let encoded_len = |frames: &[SendFrame]| {
+            frames
+                .iter()
+                .map(|frame| {
+                    let frame = match frame {
+                        SendFrame::Payload(frame) => {
+                            crate::frame::Frame::Payload(crate::frame::Payload {
+                                seq: frame.seq,
+                                data: vec![0; frame.payload_size],
+                                retransmit: false,
+                            })
+                        }
+                        SendFrame::Ping(frame) => {
+                            crate::frame::Frame::Ping(crate::frame::Ping { seq: frame.seq })
+                        }
+                        SendFrame::Retransmit(_) => unreachable!(),
+                    };
+                    frame.encoded_len()
+                })
+                .sum::<usize>()
+        };
+
+        // Two payload frames filling the MTU
+        let frames = send.send_within(now, 100);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(encoded_len(&frames), 100);
+
+        // Too small for any payload
+        let frames = send.send_within(now, 8);
+        assert!(frames
+            .iter()
+            .all(|frame| matches!(frame, SendFrame::Ping(_))));
+        assert!(encoded_len(&frames) <= 8);
+
+        // A single payload frame fits once the pings are outstanding
+        let frames = send.send_within(now, 8);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(encoded_len(&frames), 6);
+    }
+
+    #[test]
+    fn ack_updates_scheduler() {
+        let fd1 = 1;
+        let fd2 = 2;
+        let run = |scheduler_update_interval| {
+            let config = SendConfig {
+                payload_queue_size: 100,
+                scheduler_update_interval,
+                ..SendConfig::default()
+            };
+            let mut send = Sender::new(config);
+            send.add_fd(fd1);
+            send.add_fd(fd2);
+
+            // Both sockets start with the same RTT and even weights
+            let mut now = time::Instant::now();
+            let pings = send.send(now, 0);
+            now += time::Duration::from_millis(50);
+            let acks = pings
+                .iter()
+                .map(|frame| {
+                    let SendFrame::Ping(frame) = frame else {
+                        unreachable!();
+                    };
+                    (frame.fd, frame.seq, AckSpace::Ping)
+                })
+                .collect::<Vec<_>>();
+            send.ack_many(now, &acks);
+            send.scheduler.reset_to_even();
+
+            // The RTT of `fd2` keeps dropping
+            for rtt in [40, 30, 20] {
+                let frames = send.send(now, 10);
+                let seq_of = |fd| {
+                    frames
+                        .iter()
+                        .find_map(|frame| match frame {
+                            SendFrame::Payload(frame) if frame.fd == fd => Some(frame.seq),
+                            _ => None,
+                        })
+                        .unwrap()
+                };
+                let (seq1, seq2) = (seq_of(fd1), seq_of(fd2));
+                let sent = now;
+                send.ack(
+                    sent + time::Duration::from_millis(rtt),
+                    fd2,
+                    seq2,
+                    AckSpace::Payload,
+                );
+                now = sent + time::Duration::from_millis(50);
+                send.ack(now, fd1, seq1, AckSpace::Payload);
+            }
+            send.weights().clone()
+        };
+
+        let weights = run(time::Duration::ZERO);
+        assert!(weights[&fd2] > weights[&fd1]);
+
+        // Debounced past the whole run
+        let weights = run(time::Duration::from_secs(3600));
+        assert_eq!(weights[&fd1], 0.5);
+        assert_eq!(weights[&fd2], 0.5);
+    }
+
+    #[test]
+    fn ping_rtt_updates_scheduler() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fast = 1;
+        let slow = 2;
+
+        send.add_fd(fast);
+        send.add_fd(slow);
+        assert!(send.weights().is_empty());
+
+        // Only pings are sent
+        let mut now = time::Instant::now();
+        for _ in 0..3 {
+            let frames = send.send(now, 0);
+            assert_eq!(frames.len(), 2);
+            let seq_of = |fd| {
+                frames
+                    .iter()
+                    .find_map(|frame| match frame {
+                        SendFrame::Ping(frame) if frame.fd == fd => Some(frame.seq),
+                        _ => None,
+                    })
+                    .unwrap()
+            };
+            let (fast_seq, slow_seq) = (seq_of(fast), seq_of(slow));
+            let sent = now;
+            send.ack(
+                sent + time::Duration::from_millis(10),
+                fast,
+                fast_seq,
+                AckSpace::Ping,
+            );
+            now = sent + time::Duration::from_millis(50);
+            send.ack(now, slow, slow_seq, AckSpace::Ping);
+        }
+
+        assert_eq!(send.metrics().payloads_sent, 0);
+        assert!(send.weights().contains_key(&slow));
+        assert!(send.weights()[&fast] > send.weights()[&slow]);
+    }
+
+    #[test]
+    fn weight_of() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        send.add_fd(fd3);
+        assert_eq!(send.weight_of(fd1), None);
+
+        let now = time::Instant::now();
+        let frames = send
+            .send(now, 3)
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => frame,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        // Every socket times out but `ack_fd` earns back its credit
+        let now = now + config.default_rto;
+        assert!(send.retransmit_rto_payloads(now).is_err());
+        let ack_fd = frames[0].fd;
+        send.ack(now, ack_fd, frames[0].seq, AckSpace::Payload);
+        assert_eq!(send.retransmit_rto_payloads(now).unwrap().len(), 2);
+
+        assert_eq!(send.weight_of(ack_fd), Some(1.0));
+        assert_eq!(send.weight_of(frames[1].fd), Some(0.0));
+        assert_eq!(send.weight_of(4), None);
+    }
+
+    #[test]
+    fn handle_ack_frame() {
+        use crate::frame::{PayloadAck, Ping, PingAck};
+
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        let fd = 1;
+        send.add_fd(fd);
+
+        let now = time::Instant::now();
+        let frames = send.send(now, 0);
+        assert!(matches!(
+            frames[..],
+            [SendFrame::Ping(PingSendFrame { seq, .. })] if seq == Seq16::new(0)
+        ));
+        assert_eq!(send.pending_pings(), vec![fd]);
+
+        // Not an ack
+        let ping = Frame::Ping(Ping { seq: Seq16::new(0) });
+        assert_eq!(send.handle_ack_frame(now, fd, &ping), None);
+        assert_eq!(send.metrics().acks_received, 0);
+
+        let ping_ack = Frame::PingAck(PingAck { seq: Seq16::new(0) });
+        assert_eq!(
+            send.handle_ack_frame(now, fd, &ping_ack),
+            Some(AckSpace::Ping)
+        );
+        assert!(send.pending_pings().is_empty());
+
+        // The payload takes seq 0 as well, which a ping ack does not touch
+        let frames = send.send(now, 10);
+        assert!(matches!(
+            frames[..],
+            [SendFrame::Payload(PayloadSendFrame { seq, .. })] if seq == Seq16::new(0)
+        ));
+        assert_eq!(
+            send.handle_ack_frame(now, fd, &ping_ack),
+            Some(AckSpace::Ping)
+        );
+        assert_eq!(send.in_flight_bytes(), 10);
+
+        let payload_ack = Frame::PayloadAck(PayloadAck { seq: Seq16::new(0) });
+        assert_eq!(
+            send.handle_ack_frame(now, fd, &payload_ack),
+            Some(AckSpace::Payload)
+        );
+        assert_eq!(send.in_flight_bytes(), 0);
+        assert_eq!(send.metrics().acks_received, 3);
+    }
+
+    #[test]
+    fn check_rep() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            rng_seed: Some(0),
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        let fd1 = 1;
+        let fd2 = 2;
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        send.check_rep();
+
+        let now = time::Instant::now();
+        let frames = send.send(now, 10);
+        send.check_rep();
+        let SendFrame::Payload(acked) = frames[0] else {
+            unreachable!();
+        };
+        send.ack(now, acked.fd, acked.seq, AckSpace::Payload);
+        send.check_rep();
+
+        // Reassignment keeps both sides in step
+        let now = now + time::Duration::from_secs(2);
+        let _ = send.retransmit_rto_payloads(now);
+        send.check_rep();
+        let _ = send.remove_fd(fd1);
+        send.check_rep();
+        let Err(ReassignPayloadError::NoSocketsLeft { payloads }) = send.remove_fd(fd2) else {
+            unreachable!();
+        };
+        send.check_rep();
+        send.add_fd(fd1);
+        send.set_credit(fd1, Credit::Good);
+        let _ = send.reenqueue_orphaned(now, payloads.into_iter().collect());
+        send.check_rep();
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_rep_divergence() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        send.add_fd(1);
+        let now = time::Instant::now();
+        let frames = send.send(now, 10);
+        let SendFrame::Payload(frame) = frames[0] else {
+            unreachable!();
+        };
+
+        // The socket still carries the payload
+        send.payload_queue.cancel(frame.seq);
+        send.check_rep();
+    }
+
+    #[test]
+    fn apply_ack_frame() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        let fd = 1;
+        send.add_fd(fd);
+
+        let now = time::Instant::now();
+        for _ in 0..8 {
+            let _ = send.send(now, 1);
+        }
+        assert_eq!(send.in_flight_bytes(), 8);
+
+        // 2 and 5 are still missing
+        let ack = Ack {
+            cumulative: Seq16::new(2),
+            ranges: vec![
+                (Seq16::new(3), Seq16::new(4)),
+                (Seq16::new(6), Seq16::new(9)),
+            ],
+        };
+        assert_eq!(
+            send.apply_ack_frame(now, fd, &ack),
+            [0, 1, 3, 4, 6, 7].map(Seq16::new).to_vec()
+        );
+        assert_eq!(
+            send.payload_queue.pending_sequences().collect::<Vec<_>>(),
+            vec![Seq16::new(2), Seq16::new(5)]
+        );
+        assert_eq!(send.metrics().acks_received, 6);
+
+        // Already acked payloads are skipped
+        assert!(send.apply_ack_frame(now, fd, &ack).is_empty());
+        let ack = Ack {
+            cumulative: Seq16::new(8),
+            ranges: vec![],
+        };
+        assert_eq!(
+            send.handle_ack_frame(now, fd, &Frame::Ack(ack)),
+            Some(AckSpace::Payload)
+        );
+        assert_eq!(send.in_flight_bytes(), 0);
+    }
+
+    #[test]
+    fn custom_weigher() {
+        struct SlowFirst;
+
+        impl Weigher for SlowFirst {
+            fn cost(&self, _fd: RawFd, socket: &Socket) -> Option<f64> {
+                socket.rtt().map(|rtt| 1.0 / rtt.as_secs_f64())
+            }
+        }
+
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        send.set_weigher(SlowFirst);
+
+        let fast = 1;
+        let slow = 2;
+        send.add_fd(fast);
+        send.add_fd(slow);
+
+        let mut now = time::Instant::now();
+        for _ in 0..3 {
+            let frames = send.send(now, 0);
+            let sent = now;
+            for frame in frames {
+                let SendFrame::Ping(frame) = frame else {
+                    unreachable!();
+                };
+                let rtt = match frame.fd == fast {
+                    true => time::Duration::from_millis(10),
+                    false => time::Duration::from_millis(50),
+                };
+                send.ack(sent + rtt, frame.fd, frame.seq, AckSpace::Ping);
+            }
+            now = sent + time::Duration::from_millis(50);
+        }
+
+        // Start over from even weights now that both sockets have RTTs
+        send.scheduler.reset_to_even();
+        for _ in 0..3 {
+            send.update_scheduler();
+        }
+
+        // The slow socket is preferred
+        assert!(send.weights()[&slow] > send.weights()[&fast]);
+    }
+
+    #[test]
+    fn on_timeout_tick() {
+        let config = SendConfig {
+            payload_queue_size: 100,
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        for fd in 1..=3 {
+            send.add_fd(fd);
+        }
+
+        let start = time::Instant::now();
+        let frames = send
+            .send(start, 3)
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => frame,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(frames.len(), 3);
+        let data = |seq: Seq16| match seq == frames[1].seq {
+            true => Some(vec![1]),
+            // The data of the other payloads is already freed
+            false => None,
+        };
+
+        // No credible socket, so the payloads are retried on their own sockets
+        let now = start + config.default_rto;
+        let payload = Payload {
+            seq: frames[1].seq,
+            data: vec![1],
+            retransmit: true,
+        };
+        let retx = send.on_timeout_tick(now, data);
+        assert_eq!(retx, vec![(frames[1].fd, Frame::Payload(payload.clone()))]);
+
+        // The RTT sample of 1s makes the socket credible with an RTO of 2s
+        let now = now + config.default_rto;
+        let good_fd = frames[0].fd;
+        send.ack(now, good_fd, frames[0].seq, AckSpace::Payload);
+        let retx = send.on_timeout_tick(now, data);
+        assert_eq!(retx, vec![(good_fd, Frame::Payload(payload.clone()))]);
+
+        // The stopwatches restart on the new socket, skipped payloads included
+        assert!(send.on_timeout_tick(now, data).is_empty());
+        for frame in &frames[1..] {
+            let stopwatch = send.payload_queue.rtt_stopwatch(frame.seq).unwrap();
+            assert_eq!(stopwatch.start(), now);
+            assert_eq!(stopwatch.timeout(), time::Duration::from_secs(2));
+        }
+        let now = now + time::Duration::from_secs(1);
+        assert!(send.on_timeout_tick(now, data).is_empty());
+        assert_eq!(send.sockets.sockets()[&good_fd].credit(), Credit::Good);
+
+        // The next RTO discredits the only credible socket, which retries the payload
+        let now = now + time::Duration::from_secs(1);
+        let retx = send.on_timeout_tick(now, data);
+        assert_eq!(retx, vec![(good_fd, Frame::Payload(payload))]);
+        assert_eq!(send.sockets.sockets()[&good_fd].credit(), Credit::Bad);
+    }
+
+    #[test]
+    fn default_config() {
+        let config = SendConfig::default();
+        assert_eq!(config.validate(), Ok(()));
+        let mut send = Sender::new(config);
+        let fd = 1;
+        send.add_fd(fd);
+
+        let now = time::Instant::now();
+        let frames = send.send(now, 1);
+        let [SendFrame::Payload(frame)] = frames.as_slice() else {
+            unreachable!();
+        };
+        let now = now + time::Duration::from_millis(10);
+        send.ack(now, fd, frame.seq, AckSpace::Payload);
+        assert_eq!(send.payload_queue.len(), 0);
+        assert_eq!(
+            send.sockets.sockets()[&fd].rtt(),
+            Some(time::Duration::from_millis(10))
+        );
+    }
+
+    #[test]
+    fn config_builder() {
+        let config = SendConfig::builder()
+            .payload_queue_size(16)
+            .tail_loss_probe(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            config,
+            SendConfig {
+                payload_queue_size: 16,
+                tail_loss_probe: true,
+                ..SendConfig::default()
+            }
+        );
+
+        assert_eq!(
+            SendConfig::builder().learning_rate(1.0).build(),
+            Err(SendConfigError::LearningRateOutOfRange)
+        );
+    }
+
+    #[test]
+    fn aggregate_rtt() {
+        let config = SendConfig {
+            rng_seed: Some(0),
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        let fd1 = 1;
+        let fd2 = 2;
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+        assert_eq!(send.aggregate_rtt(), None);
+
+        let start = time::Instant::now();
+        let frames = send
+            .send(start, 2)
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => (frame.fd, frame.seq),
+                _ => unreachable!(),
+            })
+            .collect::<HashMap<_, _>>();
+        let fast = time::Duration::from_millis(10);
+        let slow = time::Duration::from_millis(40);
+        send.ack(start + fast, fd1, frames[&fd1], AckSpace::Payload);
+        send.ack(start + slow, fd2, frames[&fd2], AckSpace::Payload);
+
+        // One step from even weights
+        send.scheduler.reset_to_even();
+        send.update_scheduler();
+
+        // The faster socket weighs more and pulls the blend below the mean
+        let w1 = send.weight_of(fd1).unwrap();
+        let w2 = send.weight_of(fd2).unwrap();
+        assert!(w1 > w2 && w2 > 0.0);
+        let expected = (fast.as_secs_f64() * w1 + slow.as_secs_f64() * w2) / (w1 + w2);
+        let aggregate = send.aggregate_rtt().unwrap();
+        assert!((aggregate.as_secs_f64() - expected).abs() < 1e-6);
+        assert!(fast < aggregate && aggregate < (fast + slow) / 2);
+
+        // Sockets without an RTT sample time out by the blend rather than the default RTO
+        assert_eq!(send.fallback_rto(), aggregate * 2);
+    }
 }
@@ -0,0 +1,166 @@
+use std::time;
+
+/// Smooth the RTT samples of a socket.
+pub trait RttEstimator {
+    fn sample(&mut self, rtt: time::Duration);
+
+    /// The smoothed RTT, or `None` before the first sample
+    fn srtt(&self) -> Option<time::Duration>;
+
+    /// The retransmission timeout, twice `srtt` unless the estimator tracks the variance
+    fn rto(&self) -> Option<time::Duration> {
+        self.srtt().map(|srtt| srtt * 2)
+    }
+}
+
+/// Take the latest sample as is
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatestSample {
+    latest: Option<time::Duration>,
+}
+
+impl RttEstimator for LatestSample {
+    fn sample(&mut self, rtt: time::Duration) {
+        self.latest = Some(rtt);
+    }
+
+    fn srtt(&self) -> Option<time::Duration> {
+        self.latest
+    }
+}
+
+/// Exponentially weighted moving average
+#[derive(Debug, Clone, Copy)]
+pub struct Ewma {
+    /// The weight of each new sample, in `(0, 1]`
+    alpha: f64,
+    srtt: Option<time::Duration>,
+}
+
+impl Ewma {
+    #[must_use]
+    pub fn new(alpha: f64) -> Self {
+        Self { alpha, srtt: None }
+    }
+}
+
+impl RttEstimator for Ewma {
+    fn sample(&mut self, rtt: time::Duration) {
+        self.srtt = Some(match self.srtt {
+            Some(srtt) => srtt.mul_f64(1.0 - self.alpha) + rtt.mul_f64(self.alpha),
+            None => rtt,
+        });
+    }
+
+    fn srtt(&self) -> Option<time::Duration> {
+        self.srtt
+    }
+}
+
+/// The smoothed RTT and RTT variance of RFC 6298
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JacobsonKarels {
+    srtt: Option<time::Duration>,
+    rttvar: time::Duration,
+}
+
+impl RttEstimator for JacobsonKarels {
+    fn sample(&mut self, rtt: time::Duration) {
+        let Some(srtt) = self.srtt else {
+            self.srtt = Some(rtt);
+            self.rttvar = rtt / 2;
+            return;
+        };
+        let deviation = match srtt > rtt {
+            true => srtt - rtt,
+            false => rtt - srtt,
+        };
+        self.rttvar = self.rttvar * 3 / 4 + deviation / 4;
+        self.srtt = Some(srtt * 7 / 8 + rtt / 8);
+    }
+
+    fn srtt(&self) -> Option<time::Duration> {
+        self.srtt
+    }
+
+    fn rto(&self) -> Option<time::Duration> {
+        self.srtt.map(|srtt| srtt + self.rttvar * 4)
+    }
+}
+
+/// Pick the `RttEstimator` of each socket
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RttSmoothing {
+    LatestSample,
+    Ewma { alpha: f64 },
+    JacobsonKarels,
+}
+
+impl RttSmoothing {
+    #[must_use]
+    pub fn estimator(&self) -> Box<dyn RttEstimator> {
+        match *self {
+            RttSmoothing::LatestSample => Box::new(LatestSample::default()),
+            RttSmoothing::Ewma { alpha } => Box::new(Ewma::new(alpha)),
+            RttSmoothing::JacobsonKarels => Box::new(JacobsonKarels::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trajectory(estimator: &mut dyn RttEstimator, samples: &[u64]) -> Vec<time::Duration> {
+        samples
+            .iter()
+            .map(|sample| {
+                estimator.sample(time::Duration::from_millis(*sample));
+                estimator.srtt().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn noisy_samples() {
+        let samples = [100, 160, 40, 140, 60, 100];
+        let ms = time::Duration::from_millis;
+
+        let mut latest = LatestSample::default();
+        assert_eq!(latest.srtt(), None);
+        let latest = trajectory(&mut latest, &samples);
+        assert_eq!(latest, samples.map(ms).to_vec());
+
+        let mut ewma = Ewma::new(0.125);
+        let ewma = trajectory(&mut ewma, &samples);
+        let mut jk = JacobsonKarels::default();
+        let jk_srtt = trajectory(&mut jk, &samples);
+
+        // Both smooth with a gain of 1/8
+        for (ewma, jk) in ewma.iter().zip(&jk_srtt) {
+            assert!(ewma.abs_diff(*jk) < time::Duration::from_micros(1));
+        }
+        for srtt in &ewma {
+            assert!(ms(95) < *srtt && *srtt < ms(110));
+        }
+
+        // The noise widens the timeout beyond twice the smoothed RTT
+        let srtt = jk.srtt().unwrap();
+        assert!(jk.rto().unwrap() > srtt * 2);
+        assert_eq!(Ewma::new(0.125).rto(), None);
+    }
+
+    #[test]
+    fn steady_samples() {
+        let mut jk = JacobsonKarels::default();
+        for _ in 0..50 {
+            jk.sample(time::Duration::from_millis(100));
+        }
+
+        // The variance decays
+        let rto = jk.rto().unwrap();
+        assert!(rto < time::Duration::from_millis(101));
+        assert_eq!(jk.srtt(), Some(time::Duration::from_millis(100)));
+    }
+}
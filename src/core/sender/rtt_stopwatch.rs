@@ -1,31 +1,76 @@
 use std::time;
 
-pub struct RttStopwatch {
-    start: time::Instant,
+use super::Timestamp;
+
+pub struct RttStopwatch<T = time::Instant> {
+    start: T,
     timeout: time::Duration,
 }
 
-impl RttStopwatch {
+impl<T> RttStopwatch<T>
+where
+    T: Timestamp,
+{
     #[must_use]
-    pub fn new(now: time::Instant, timeout: time::Duration) -> Self {
+    pub fn new(now: T, timeout: time::Duration) -> Self {
         Self {
             start: now,
             timeout,
         }
     }
 
+    /// Saturate at zero if `now` precedes the start, e.g. with a non-monotonic clock.
+    #[must_use]
+    pub fn elapsed(&self, now: T) -> time::Duration {
+        now.saturating_elapsed(self.start)
+    }
+
     #[must_use]
-    pub fn has_timed_out(&self, now: time::Instant) -> bool {
-        now - self.start >= self.timeout
+    pub fn has_timed_out(&self, now: T) -> bool {
+        self.elapsed(now) >= self.timeout
     }
 
     #[must_use]
-    pub fn into_rtt(self, now: time::Instant) -> time::Duration {
-        now - self.start
+    pub fn into_rtt(self, now: T) -> time::Duration {
+        self.elapsed(now)
     }
 
     #[must_use]
     pub fn timeout(&self) -> time::Duration {
         self.timeout
     }
+
+    #[must_use]
+    pub fn start(&self) -> T {
+        self.start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sender::MockInstant;
+
+    #[test]
+    fn elapsed() {
+        let start = MockInstant::from_epoch(time::Duration::from_secs(10));
+        let stopwatch = RttStopwatch::new(start, time::Duration::from_secs(1));
+        let now = start + time::Duration::from_millis(300);
+        assert_eq!(stopwatch.elapsed(now), time::Duration::from_millis(300));
+        assert!(!stopwatch.has_timed_out(now));
+        assert_eq!(stopwatch.into_rtt(now), time::Duration::from_millis(300));
+    }
+
+    #[test]
+    fn now_before_start() {
+        let start = MockInstant::from_epoch(time::Duration::from_secs(10));
+        let now = MockInstant::from_epoch(time::Duration::from_secs(9));
+        let stopwatch = RttStopwatch::new(start, time::Duration::from_secs(0));
+        assert_eq!(stopwatch.elapsed(now), time::Duration::ZERO);
+        assert!(stopwatch.has_timed_out(now));
+        assert_eq!(stopwatch.into_rtt(now), time::Duration::ZERO);
+
+        let stopwatch = RttStopwatch::new(start, time::Duration::from_secs(1));
+        assert!(!stopwatch.has_timed_out(now));
+    }
 }
@@ -0,0 +1,312 @@
+use std::{collections::HashMap, os::fd::RawFd, time};
+
+use seq::Seq16;
+use thiserror::Error;
+
+use super::{Credit, SendConfig, SendConfigError, Sender, TimedSendQueue, Timestamp};
+use crate::core::{signed_dist, MAX_WINDOW};
+
+/// The position of a socket among the sockets of a `Sender` in ascending order of their fds
+///
+/// Fds do not survive a migration, so a snapshot refers to its sockets by index.
+pub type SocketIndex = usize;
+
+/// The state a `Sender` needs to continue a connection on other sockets, e.g. in another process.
+///
+/// The timestamps are not captured, so the restored payloads time out again from the time of `Sender::restore`.
+/// Neither are the weigher set by `Sender::set_weigher`, the metrics, or the credit observer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SendSnapshot {
+    pub config: SendConfig,
+    /// Indexed by `SocketIndex`
+    pub sockets: Vec<SocketSnapshot>,
+    /// The payloads not yet acked, the oldest first
+    pub outstanding: Vec<OutstandingPayload>,
+    /// The sequence number of the next new payload
+    #[cfg_attr(feature = "serde", serde(with = "crate::frame::seq16_serde"))]
+    pub next_seq: Seq16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketSnapshot {
+    /// The scheduler weight, or `None` if the scheduler has not weighted the socket
+    pub weight: Option<f64>,
+    /// The smoothed RTT
+    pub rtt: Option<time::Duration>,
+    pub credit: Credit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutstandingPayload {
+    #[cfg_attr(feature = "serde", serde(with = "crate::frame::seq16_serde"))]
+    pub seq: Seq16,
+    /// The socket carrying the payload, or `None` if the payload is orphaned
+    pub socket: Option<SocketIndex>,
+    pub size: usize,
+}
+
+impl<T> Sender<T>
+where
+    T: Timestamp,
+{
+    #[must_use]
+    pub fn snapshot(&self) -> SendSnapshot {
+        let fds = self.fds();
+        let sockets = fds
+            .iter()
+            .map(|fd| {
+                let socket = &self.sockets.sockets()[fd];
+                SocketSnapshot {
+                    weight: self.scheduler.weights().get(fd).copied(),
+                    rtt: socket.rtt(),
+                    credit: socket.credit(),
+                }
+            })
+            .collect();
+
+        let next_seq = self.payload_queue.next_seq();
+        let mut outstanding = self
+            .payload_queue
+            .pending_sequences()
+            .chain(self.orphaned.iter().copied())
+            .map(|seq| OutstandingPayload {
+                seq,
+                socket: self
+                    .sockets
+                    .payload_fd(seq)
+                    .and_then(|fd| fds.binary_search(&fd).ok()),
+                size: self.payload_sizes.get(&seq).copied().unwrap_or(0),
+            })
+            .collect::<Vec<_>>();
+        outstanding.sort_unstable_by_key(|payload| signed_dist(next_seq, payload.seq));
+
+        SendSnapshot {
+            config: self.config,
+            sockets,
+            outstanding,
+            next_seq,
+        }
+    }
+
+    /// Continue the connection of `snapshot` on the sockets of `fd_map`.
+    ///
+    /// The outstanding payloads start timing out again from `now`.
+    /// The payloads of a socket missing from `fd_map` are orphaned; see `reenqueue_orphaned`.
+    ///
+    /// Fail if the config of `snapshot` is invalid or its outstanding payloads do not fit in `MAX_WINDOW`, e.g. if it was tampered with on the way.
+    pub fn restore(
+        now: T,
+        snapshot: SendSnapshot,
+        fd_map: HashMap<SocketIndex, RawFd>,
+    ) -> Result<Self, RestoreError> {
+        let mut this = Self::try_new(snapshot.config).map_err(RestoreError::InvalidConfig)?;
+
+        // Restore sockets
+        let mut indices = fd_map
+            .keys()
+            .copied()
+            .filter(|index| *index < snapshot.sockets.len())
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        for index in &indices {
+            let fd = fd_map[index];
+            let socket = &snapshot.sockets[*index];
+            this.sockets.add_fd(fd);
+            if let Some(rtt) = socket.rtt {
+                this.sockets.seed_rtt(fd, rtt);
+            }
+            this.sockets.set_credit(fd, socket.credit);
+        }
+        // Nobody observes the restored credits
+        let _ = this.sockets.take_credit_transitions();
+
+        // Restore weights
+        let weights = indices
+            .iter()
+            .filter_map(|index| Some((fd_map[index], snapshot.sockets[*index].weight?)))
+            .collect();
+        this.scheduler.set_weights(weights);
+
+        // Restore the whole span even if it exceeds the payload queue, which then takes no new payloads until enough are acked
+        let start = snapshot
+            .outstanding
+            .first()
+            .map(|payload| payload.seq)
+            .unwrap_or(snapshot.next_seq);
+        this.payload_queue = TimedSendQueue::with_start(MAX_WINDOW, start);
+        let mut outstanding = snapshot.outstanding.iter().peekable();
+        while this.payload_queue.next_seq() != snapshot.next_seq {
+            let seq = this.payload_queue.next_seq();
+            let Some(payload) = outstanding.next_if(|payload| payload.seq == seq) else {
+                // Acked before the snapshot
+                if this.payload_queue.skip().is_none() {
+                    return Err(RestoreError::WindowExceeded);
+                }
+                continue;
+            };
+            this.payload_sizes.insert(seq, payload.size);
+            let fd = payload
+                .socket
+                .filter(|index| *index < snapshot.sockets.len())
+                .and_then(|index| fd_map.get(&index).copied());
+            let Some(fd) = fd else {
+                // Orphaned before the snapshot or on a socket left behind
+                if this.payload_queue.reserve().is_none() {
+                    return Err(RestoreError::WindowExceeded);
+                }
+                this.orphaned.insert(seq);
+                continue;
+            };
+            let timeout = this.sockets.sockets()[&fd]
+                .rto()
+                .unwrap_or_else(|| this.fallback_rto());
+            if this.payload_queue.send(now, timeout, fd).is_none() {
+                return Err(RestoreError::WindowExceeded);
+            }
+            this.sockets.send_payload(fd, seq, now);
+        }
+        this.payload_queue
+            .set_capacity(snapshot.config.payload_queue_size);
+
+        Ok(this)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum RestoreError {
+    #[error("invalid send config: {0}")]
+    InvalidConfig(SendConfigError),
+    #[error("outstanding payloads exceed the max window")]
+    WindowExceeded,
+}
+
+#[cfg(test)]
+mod tests {
+    use rep::CheckRep;
+
+    use super::*;
+    use crate::core::sender::{AckSpace, SendFrame};
+
+    #[test]
+    fn roundtrip() {
+        let config = SendConfig {
+            rng_seed: Some(0),
+            ..SendConfig::default()
+        };
+        let mut send = Sender::new(config);
+        let fd1 = 1;
+        let fd2 = 2;
+        send.add_fd(fd1);
+        send.add_fd(fd2);
+
+        // Earn distinct RTTs and weights
+        let start = time::Instant::now();
+        let payloads = |frames: Vec<SendFrame>| {
+            frames
+                .into_iter()
+                .filter_map(|frame| match frame {
+                    SendFrame::Payload(frame) => Some((frame.fd, frame.seq)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let probes = payloads(send.send(start, 2))
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        send.ack(
+            start + time::Duration::from_millis(10),
+            fd1,
+            probes[&fd1],
+            AckSpace::Payload,
+        );
+        send.ack(
+            start + time::Duration::from_millis(40),
+            fd2,
+            probes[&fd2],
+            AckSpace::Payload,
+        );
+        send.scheduler.reset_to_even();
+        send.update_scheduler();
+
+        // Leave a gap among the outstanding payloads
+        let now = start + time::Duration::from_millis(50);
+        let mut sent = Vec::new();
+        for _ in 0..3 {
+            sent.extend(payloads(send.send(now, 2)));
+        }
+        let (acked_fd, acked_seq) = sent[2];
+        send.ack(now, acked_fd, acked_seq, AckSpace::Payload);
+        let snapshot = send.snapshot();
+        assert_eq!(snapshot.outstanding.len(), sent.len() - 1);
+
+        let new_fd1 = 10;
+        let new_fd2 = 20;
+        let fd_map = HashMap::from([(0, new_fd1), (1, new_fd2)]);
+        let mut restored = Sender::restore(now, snapshot.clone(), fd_map).unwrap();
+        restored.check_rep();
+
+        for (fd, new_fd) in [(fd1, new_fd1), (fd2, new_fd2)] {
+            let weight = send.weight_of(fd).unwrap();
+            assert!((restored.weight_of(new_fd).unwrap() - weight).abs() < 1e-9);
+            assert_eq!(
+                restored.sockets.sockets()[&new_fd].payloads(),
+                send.sockets.sockets()[&fd].payloads()
+            );
+            assert_eq!(
+                restored.sockets.sockets()[&new_fd].rtt(),
+                send.sockets.sockets()[&fd].rtt()
+            );
+        }
+        assert_eq!(restored.in_flight_bytes(), send.in_flight_bytes());
+        assert_eq!(restored.snapshot().outstanding, snapshot.outstanding);
+
+        // New payloads continue the sequence
+        let (_, seq) = payloads(restored.send(now, 1))[0];
+        assert_eq!(seq, snapshot.next_seq);
+    }
+
+    #[test]
+    fn restore_invalid() {
+        let outstanding = (0..=MAX_WINDOW as u16)
+            .map(|seq| OutstandingPayload {
+                seq: Seq16::new(seq),
+                socket: Some(0),
+                size: 1,
+            })
+            .collect::<Vec<_>>();
+        let snapshot = SendSnapshot {
+            config: SendConfig::default(),
+            sockets: vec![SocketSnapshot {
+                weight: None,
+                rtt: None,
+                credit: Credit::Good,
+            }],
+            next_seq: Seq16::new(outstanding.len() as u16),
+            outstanding,
+        };
+        let fd_map = HashMap::from([(0, 1)]);
+        let now = time::Instant::now();
+
+        // More payloads than the window takes
+        let res = Sender::restore(now, snapshot.clone(), fd_map.clone());
+        assert_eq!(res.err(), Some(RestoreError::WindowExceeded));
+
+        let snapshot = SendSnapshot {
+            config: SendConfig {
+                payload_queue_size: 0,
+                ..SendConfig::default()
+            },
+            ..snapshot
+        };
+        let res = Sender::restore(now, snapshot, fd_map);
+        assert_eq!(
+            res.err(),
+            Some(RestoreError::InvalidConfig(
+                SendConfigError::ZeroPayloadQueueSize
+            ))
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, VecDeque},
     os::fd::RawFd,
     time,
 };
@@ -7,17 +7,36 @@ use std::{
 use rep::*;
 use seq::Seq16;
 
-use super::TimedSendQueue;
+use super::{RttEstimator, RttSmoothing, SplitMix64, TimedSendQueue, Timestamp};
 
 #[derive(CheckIndieFields)]
-pub struct Sockets {
+pub struct Sockets<T = time::Instant> {
     /// Payload-to-socket mappings
     payload_fds: HashMap<Seq16, RawFd>,
 
-    sockets: HashMap<RawFd, Socket>,
+    sockets: HashMap<RawFd, Socket<T>>,
+
+    /// Credit transitions not yet taken by the observer
+    credit_transitions: Vec<CreditTransition>,
+
+    /// Randomizes payload reassignment
+    rng: SplitMix64,
+
+    /// Consecutive RTT samples that turn a `Bad` socket `Good`
+    credit_samples: usize,
+    /// Consecutive RTOs that turn a `Good` socket `Bad`
+    discredit_rtos: usize,
+
+    /// The most payloads a socket takes on RTO reassignment
+    socket_window: Option<usize>,
+
+    /// The RTT estimator of each new socket
+    rtt_smoothing: RttSmoothing,
+    /// The RTT samples each new socket keeps for percentiles
+    rtt_history: usize,
 }
 
-impl CheckFields for Sockets {
+impl<T> CheckFields for Sockets<T> {
     fn check_fields(&self, e: &mut RepErrors) {
         // Check payload-to-socket-to-payload consistency
         for (seq, fd) in self.payload_fds.iter() {
@@ -59,24 +78,76 @@ impl CheckFields for Sockets {
     }
 }
 
-impl CheckRep for Sockets {}
+impl<T> CheckRep for Sockets<T> {}
 
 #[check_rep]
-impl Sockets {
+impl<T> Sockets<T>
+where
+    T: Timestamp,
+{
     #[must_use]
     pub fn new() -> Self {
+        Self::with_rng(SplitMix64::from_entropy())
+    }
+
+    /// Reassign payloads reproducibly
+    #[must_use]
+    pub fn with_rng(rng: SplitMix64) -> Self {
         Self {
             payload_fds: HashMap::new(),
             sockets: HashMap::new(),
+            credit_transitions: Vec::new(),
+            rng,
+            credit_samples: 1,
+            discredit_rtos: 1,
+            socket_window: None,
+            rtt_smoothing: RttSmoothing::LatestSample,
+            rtt_history: DEFAULT_RTT_HISTORY,
         }
     }
 
+    /// Require `credit_samples` consecutive RTT samples to go `Bad -> Good` and `discredit_rtos` consecutive RTOs to go `Good -> Bad`, so a marginal socket does not flap.
+    ///
+    /// Both default to `1`.
+    pub fn set_credit_hysteresis(&mut self, credit_samples: usize, discredit_rtos: usize) {
+        self.credit_samples = credit_samples;
+        self.discredit_rtos = discredit_rtos;
+    }
+
+    /// Stop reassigning RTO payloads to a socket once it carries `socket_window` payloads.
+    ///
+    /// `None` by default, which leaves sockets unbounded.
+    pub fn set_socket_window(&mut self, socket_window: Option<usize>) {
+        self.socket_window = socket_window;
+    }
+
+    /// Smooth the RTT samples of sockets added from now on.
+    ///
+    /// `RttSmoothing::LatestSample` by default.
+    pub fn set_rtt_smoothing(&mut self, rtt_smoothing: RttSmoothing) {
+        self.rtt_smoothing = rtt_smoothing;
+    }
+
+    /// Keep the latest `rtt_history` RTT samples of sockets added from now on; see `Socket::rtt_percentile`.
+    ///
+    /// `64` by default.
+    pub fn set_rtt_history(&mut self, rtt_history: usize) {
+        self.rtt_history = rtt_history;
+    }
+
     pub fn add_fd(&mut self, fd: RawFd) {
-        self.sockets.insert(fd, Socket::new());
+        let mut socket = Socket::with_rtt_estimator(self.rtt_smoothing.estimator());
+        socket.rtt_history = VecDeque::with_capacity(self.rtt_history);
+        socket.rtt_history_len = self.rtt_history;
+        self.sockets.insert(fd, socket);
     }
 
-    #[must_use]
-    pub fn remove_fd(&mut self, fd: RawFd) -> Result<RetransmitPayloads, ReassignPayloadError> {
+    /// The payloads of the removed socket are reassigned by `weights`.
+    pub fn remove_fd(
+        &mut self,
+        fd: RawFd,
+        weights: &HashMap<RawFd, f64>,
+    ) -> Result<RetransmitPayloads, ReassignPayloadError> {
         let Some(socket) = self.sockets.remove(&fd) else {
             // Socket was already removed
             return Ok(Vec::new());
@@ -102,37 +173,87 @@ impl Sockets {
         // The remaining sockets will be assigned the payloads of the removed socket
         let applicable_sockets = self.sockets.keys().copied().collect();
 
-        // Round-robin assign payloads to other sockets
-        self.round_robin_reassign_payloads(socket.payloads.into_iter(), applicable_sockets)
+        // Weighted assign payloads to other sockets
+        self.weighted_reassign_payloads(
+            socket.payloads,
+            applicable_sockets,
+            weights,
+            HashMap::new(),
+        )
+        .map(|reassignment| reassignment.assigned)
+    }
+
+    /// Assign payloads that were orphaned by the removal of the last socket to the current credible sockets.
+    ///
+    /// Fail with all the payloads if there is no credible socket.
+    pub fn reassign_orphaned_payloads(
+        &mut self,
+        payloads: impl IntoIterator<Item = Seq16>,
+        weights: &HashMap<RawFd, f64>,
+    ) -> Result<RetransmitPayloads, ReassignPayloadError> {
+        let applicable_sockets = self.credible_fds();
+
+        // Weighted assign payloads to the sockets
+        self.weighted_reassign_payloads(payloads, applicable_sockets, weights, HashMap::new())
+            .map(|reassignment| reassignment.assigned)
     }
 
     #[must_use]
-    pub fn send_ping(&mut self, fd: RawFd, now: time::Instant) -> Option<Seq16> {
+    pub fn send_ping(&mut self, fd: RawFd, now: T) -> Option<Seq16> {
         let Some(socket) = self.sockets.get_mut(&fd) else {
             // Socket was already removed
             return None;
         };
-        socket
+        let seq = socket
             .ping_queue
-            .send(now, time::Duration::from_secs(0), fd)
+            .send(now, time::Duration::from_secs(0), fd)?;
+        socket.last_send = Some(now);
+        Some(seq)
     }
 
     #[must_use]
-    pub fn sockets(&self) -> &HashMap<RawFd, Socket> {
+    pub fn sockets(&self) -> &HashMap<RawFd, Socket<T>> {
         &self.sockets
     }
 
-    pub fn send_payload(&mut self, fd: RawFd, seq: Seq16) {
+    /// Return the socket the payload is assigned to, if it is still outstanding.
+    #[must_use]
+    pub fn payload_fd(&self, seq: Seq16) -> Option<RawFd> {
+        self.payload_fds.get(&seq).copied()
+    }
+
+    /// Also retire the in-flight ping of the socket since the payload RTT sample supersedes it.
+    pub fn send_payload(&mut self, fd: RawFd, seq: Seq16, now: T) {
         self.reassign_payload_seq(fd, seq);
+
+        let Some(socket) = self.sockets.get_mut(&fd) else {
+            // Socket was already removed
+            return;
+        };
+        socket.last_send = Some(now);
+        while let Some(ping) = socket.ping_queue.newest_unacked() {
+            socket.ping_queue.cancel(ping);
+        }
     }
 
-    pub fn ack(&mut self, receiving_fd: RawFd, seq: Seq16, space: AckSpace) {
+    /// Return the new RTT sample, if any.
+    pub fn ack(
+        &mut self,
+        receiving_fd: RawFd,
+        seq: Seq16,
+        space: AckSpace<T>,
+    ) -> Option<time::Duration> {
+        // The ack proves the receiving socket is alive
+        if let Some(socket) = self.sockets.get_mut(&receiving_fd) {
+            socket.last_ack = Some(space.now());
+        }
+
         // Summarize RTT
-        let (socket, rtt) = match space {
-            AckSpace::Payload { rtt } => {
+        let (fd, socket, rtt) = match space {
+            AckSpace::Payload { rtt, .. } => {
                 let Some(assigned_fd) = self.remove_payload_seq(seq) else {
                     // Payload was already acked
-                    return;
+                    return None;
                 };
                 if assigned_fd != receiving_fd {
                     // Payload was retransmitted on a different socket (`assigned_fd`) than the ACK-receiving socket (`receiving_fd`)
@@ -140,46 +261,144 @@ impl Sockets {
                 };
                 let Some(socket) = self.sockets.get_mut(&assigned_fd) else {
                     // Socket was already removed
-                    return;
+                    return None;
                 };
 
-                (socket, rtt)
+                (assigned_fd, socket, rtt)
             }
             AckSpace::Ping { now } => {
                 let Some(socket) = self.sockets.get_mut(&receiving_fd) else {
-                    return;
+                    return None;
                 };
                 let rtt = socket.ping_queue.ack(seq, now, receiving_fd);
-                (socket, rtt)
+                (receiving_fd, socket, rtt)
+            }
+            AckSpace::Both { rtt, now } => {
+                self.remove_payload_seq(seq);
+                let Some(socket) = self.sockets.get_mut(&receiving_fd) else {
+                    return None;
+                };
+                let ping_rtt = socket.ping_queue.ack(seq, now, receiving_fd);
+
+                // Prefer the payload RTT sample
+                (receiving_fd, socket, rtt.or(ping_rtt))
             }
         };
 
         // Update socket RTT and credit
         if let Some(rtt) = rtt {
-            socket.rtt = Some(rtt);
-            socket.credit = Credit::Good;
+            socket.rtt_estimator.sample(rtt);
+            socket.record_rtt(rtt);
+            socket.rto_streak = 0;
+            socket.sample_streak += 1;
+            if socket.credit == Credit::Bad && socket.sample_streak >= self.credit_samples {
+                socket.credit = Credit::Good;
+                self.credit_transitions
+                    .push((fd, Credit::Bad, Credit::Good));
+            }
         }
+        rtt
     }
 
     /// Prevent the socket from being assigned with RTO payloads
     fn discredit(&mut self, seq: Seq16) {
-        if let Some(socket) = self.socket_mut(seq) {
+        let Some(&fd) = self.payload_fds.get(&seq) else {
+            // Payload was already acked
+            return;
+        };
+        let discredit_rtos = self.discredit_rtos;
+        let Some(socket) = self.socket_mut(seq) else {
+            return;
+        };
+        socket.payloads_rto += 1;
+        socket.sample_streak = 0;
+        socket.rto_streak += 1;
+        if socket.credit == Credit::Good && socket.rto_streak >= discredit_rtos {
             socket.credit = Credit::Bad;
+            self.credit_transitions
+                .push((fd, Credit::Good, Credit::Bad));
+        }
+    }
+
+    /// Override the credit of `fd`, e.g. when the caller knows the path is broken.
+    pub fn set_credit(&mut self, fd: RawFd, credit: Credit) {
+        let Some(socket) = self.sockets.get_mut(&fd) else {
+            return;
+        };
+        let old = socket.credit;
+        socket.credit = credit;
+        socket.sample_streak = 0;
+        socket.rto_streak = 0;
+        if old != credit {
+            self.credit_transitions.push((fd, old, credit));
+        }
+    }
+
+    /// Feed `rtt` to the estimator of `fd` without touching its credit, e.g. to restore a migrated socket.
+    pub fn seed_rtt(&mut self, fd: RawFd, rtt: time::Duration) {
+        let Some(socket) = self.sockets.get_mut(&fd) else {
+            return;
+        };
+        socket.rtt_estimator.sample(rtt);
+        socket.record_rtt(rtt);
+    }
+
+    /// Count `bytes` of acked payloads toward the bandwidth of `fd`.
+    pub fn record_delivery(&mut self, fd: RawFd, bytes: usize, now: T) {
+        let Some(socket) = self.sockets.get_mut(&fd) else {
+            return;
+        };
+        socket.delivery_start.get_or_insert(now);
+        socket.bytes_delivered += bytes;
+    }
+
+    /// Forget the loss history of every socket.
+    pub fn reset_stats(&mut self) {
+        for socket in self.sockets.values_mut() {
+            socket.reset_stats();
         }
     }
 
+    /// Take the credit transitions recorded since the last call.
+    #[must_use]
+    pub fn take_credit_transitions(&mut self) -> Vec<CreditTransition> {
+        std::mem::take(&mut self.credit_transitions)
+    }
+
+    /// Fail with all the payloads only if there is no credible socket.
+    /// Otherwise the payloads that no credible socket has room for are left to their sockets and returned as unplaced; see `set_socket_window`.
     #[must_use]
     pub fn reassign_rto_payloads(
         &mut self,
         rto_payloads: &[Seq16],
-    ) -> Result<RetransmitPayloads, ReassignPayloadError> {
+        weights: &HashMap<RawFd, f64>,
+    ) -> Result<RtoReassignment, ReassignPayloadError> {
         // Discredit sockets that have caused RTOs
         for seq in rto_payloads {
             self.discredit(*seq);
         }
 
-        let applicable_sockets = self
-            .sockets
+        let applicable_sockets = self.credible_fds();
+
+        // The room left in each credible socket
+        let mut room = HashMap::new();
+        if let Some(window) = self.socket_window {
+            for fd in &applicable_sockets {
+                let left = window.saturating_sub(self.sockets[fd].payloads.len());
+                room.insert(*fd, left);
+            }
+        }
+
+        self.weighted_reassign_payloads(
+            rto_payloads.iter().copied(),
+            applicable_sockets,
+            weights,
+            room,
+        )
+    }
+
+    fn credible_fds(&self) -> Vec<RawFd> {
+        self.sockets
             .iter()
             .filter_map(|(fd, socket)| {
                 if let Credit::Good = socket.credit {
@@ -188,38 +407,80 @@ impl Sockets {
                     None
                 }
             })
-            .collect();
-
-        self.round_robin_reassign_payloads(rto_payloads.iter().map(|seq| *seq), applicable_sockets)
+            .collect()
     }
 
-    #[must_use]
-    fn round_robin_reassign_payloads(
+    /// Spread payloads over `applicable_sockets` in proportion to `weights`.
+    ///
+    /// The `i`-th of `n` payloads goes to the socket whose cumulative weight interval contains `(i + u) / n`, where `u` is drawn once per call, so each socket gets its share give or take one payload.
+    /// Sockets are weighted evenly if none of them has a positive weight.
+    ///
+    /// A socket listed in `room` takes at most that many payloads and the overflow goes to the lowest fd with room left; sockets not listed are unbounded.
+    fn weighted_reassign_payloads(
         &mut self,
         payloads: impl IntoIterator<Item = Seq16>,
-        applicable_sockets: Vec<RawFd>,
-    ) -> Result<RetransmitPayloads, ReassignPayloadError> {
+        mut applicable_sockets: Vec<RawFd>,
+        weights: &HashMap<RawFd, f64>,
+        mut room: HashMap<RawFd, usize>,
+    ) -> Result<RtoReassignment, ReassignPayloadError> {
         if applicable_sockets.len() == 0 {
             return Err(ReassignPayloadError::NoSocketsLeft {
                 payloads: payloads.into_iter().collect(),
             });
         };
 
+        // Normalize the weights of the applicable sockets
+        applicable_sockets.sort_unstable();
+        let mut socket_weights = applicable_sockets
+            .iter()
+            .map(|fd| weights.get(fd).copied().unwrap_or(0.0).max(0.0))
+            .collect::<Vec<_>>();
+        let sum: f64 = socket_weights.iter().sum();
+        match sum > 0.0 {
+            true => socket_weights.iter_mut().for_each(|weight| *weight /= sum),
+            false => socket_weights.fill(1.0 / applicable_sockets.len() as f64),
+        }
+
+        let payloads = payloads.into_iter().collect::<Vec<_>>();
         let mut assigned_payloads = Vec::new();
+        let mut unplaced = Vec::new();
+
+        // Assign each payload by its position in the cumulative weights
+        let offset = self.rng.next_f64();
+        for (i, seq) in payloads.iter().enumerate() {
+            let position = (i as f64 + offset) / payloads.len() as f64;
+            let mut cumulative = 0.0;
+            let mut assignee = applicable_sockets[applicable_sockets.len() - 1];
+            for (fd, weight) in applicable_sockets.iter().zip(&socket_weights) {
+                cumulative += weight;
+                if position < cumulative {
+                    assignee = *fd;
+                    break;
+                }
+            }
 
-        // Round-robin assign payloads to other sockets
-        let mut round_robin = applicable_sockets.iter().cycle();
-        for seq in payloads {
-            let Some(assignee) = round_robin.next() else {
-                unreachable!();
-            };
-            assigned_payloads.push((*assignee, seq));
+            // Overflow to the first socket with room left
+            let has_room = |fd: &RawFd| room.get(fd).is_none_or(|left| *left > 0);
+            if !has_room(&assignee) {
+                let Some(fd) = applicable_sockets.iter().copied().find(has_room) else {
+                    unplaced.push(*seq);
+                    continue;
+                };
+                assignee = fd;
+            }
+            if let Some(left) = room.get_mut(&assignee) {
+                *left -= 1;
+            }
+            assigned_payloads.push((assignee, *seq));
 
             // Reassign the payload to the new socket
-            self.reassign_payload_seq(*assignee, seq)
+            self.reassign_payload_seq(assignee, *seq)
         }
 
-        Ok(assigned_payloads)
+        Ok(RtoReassignment {
+            assigned: assigned_payloads,
+            unplaced,
+        })
     }
 
     fn reassign_payload_seq(&mut self, assignee: RawFd, seq: Seq16) {
@@ -230,6 +491,7 @@ impl Sockets {
         self.payload_fds.insert(seq, assignee);
         if let Some(socket) = self.socket_mut(seq) {
             socket.payloads.insert(seq);
+            socket.payloads_sent += 1;
         }
     }
 
@@ -249,7 +511,7 @@ impl Sockets {
     ///
     /// - Payload was already acked
     /// - Socket was already removed
-    fn socket_mut(&mut self, seq: Seq16) -> Option<&mut Socket> {
+    fn socket_mut(&mut self, seq: Seq16) -> Option<&mut Socket<T>> {
         let Some(fd) = self.payload_fds.get(&seq) else {
             // Payload was already acked
             return None;
@@ -265,45 +527,177 @@ impl Sockets {
     }
 }
 
-pub struct Socket {
-    ping_queue: TimedSendQueue<RawFd>,
-    rtt: Option<time::Duration>,
+pub struct Socket<T = time::Instant> {
+    ping_queue: TimedSendQueue<RawFd, T>,
+    rtt_estimator: Box<dyn RttEstimator>,
+    /// The latest RTT samples, the oldest first
+    rtt_history: VecDeque<time::Duration>,
+    /// The most samples `rtt_history` keeps
+    rtt_history_len: usize,
     payloads: BTreeSet<Seq16>,
     credit: Credit,
+    /// The last time an ack was received on this socket
+    last_ack: Option<T>,
+    /// The last time a payload or a ping was sent on this socket
+    last_send: Option<T>,
+    /// Payloads sent or retransmitted on this socket
+    payloads_sent: usize,
+    /// Payloads that have timed out on this socket
+    payloads_rto: usize,
+    /// RTT samples since the last RTO
+    sample_streak: usize,
+    /// RTOs since the last RTT sample
+    rto_streak: usize,
+    /// Bytes of the payloads acked on this socket
+    bytes_delivered: usize,
+    /// The first ack counted in `bytes_delivered`
+    delivery_start: Option<T>,
 }
 
-impl Socket {
+impl<T> Socket<T>
+where
+    T: Timestamp,
+{
     #[must_use]
     pub fn new() -> Self {
+        Self::with_rtt_estimator(RttSmoothing::LatestSample.estimator())
+    }
+
+    #[must_use]
+    pub fn with_rtt_estimator(rtt_estimator: Box<dyn RttEstimator>) -> Self {
         Self {
             ping_queue: TimedSendQueue::new(1),
-            rtt: None,
+            rtt_estimator,
+            rtt_history: VecDeque::with_capacity(DEFAULT_RTT_HISTORY),
+            rtt_history_len: DEFAULT_RTT_HISTORY,
             payloads: BTreeSet::new(),
             credit: Credit::Bad,
+            last_ack: None,
+            last_send: None,
+            payloads_sent: 0,
+            payloads_rto: 0,
+            sample_streak: 0,
+            rto_streak: 0,
+            bytes_delivered: 0,
+            delivery_start: None,
         }
     }
 
+    /// The smoothed RTT
     pub fn rtt(&self) -> Option<time::Duration> {
-        self.rtt
+        self.rtt_estimator.srtt()
+    }
+
+    /// The retransmission timeout derived from the RTT samples
+    pub fn rto(&self) -> Option<time::Duration> {
+        self.rtt_estimator.rto()
+    }
+
+    /// The nearest-rank `p` percentile of the latest RTT samples, e.g. `0.95` for p95.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not in `[0, 1]`.
+    #[must_use]
+    pub fn rtt_percentile(&self, p: f64) -> Option<time::Duration> {
+        assert!((0.0..=1.0).contains(&p));
+        if self.rtt_history.is_empty() {
+            return None;
+        }
+        let mut samples = self.rtt_history.iter().copied().collect::<Vec<_>>();
+        samples.sort_unstable();
+        let rank = (p * samples.len() as f64).ceil() as usize;
+        Some(samples[rank.saturating_sub(1)])
+    }
+
+    /// Overwrite the oldest sample once the history is full.
+    fn record_rtt(&mut self, rtt: time::Duration) {
+        if self.rtt_history_len == 0 {
+            return;
+        }
+        if self.rtt_history.len() == self.rtt_history_len {
+            self.rtt_history.pop_front();
+        }
+        self.rtt_history.push_back(rtt);
     }
 
     pub fn credit(&self) -> Credit {
         self.credit
     }
+
+    /// Whether a ping is sent on this socket but not yet acked
+    pub fn has_pending_ping(&self) -> bool {
+        self.ping_queue.pending_count() > 0
+    }
+
+    /// Payloads assigned to this socket that are not yet acked
+    pub fn payloads(&self) -> &BTreeSet<Seq16> {
+        &self.payloads
+    }
+
+    pub fn last_ack(&self) -> Option<T> {
+        self.last_ack
+    }
+
+    pub fn last_send(&self) -> Option<T> {
+        self.last_send
+    }
+
+    /// The fraction of payloads sent on this socket that have timed out, or `0` if none were sent
+    pub fn loss_rate(&self) -> f64 {
+        if self.payloads_sent == 0 {
+            return 0.0;
+        }
+        self.payloads_rto as f64 / self.payloads_sent as f64
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.payloads_sent = 0;
+        self.payloads_rto = 0;
+        self.bytes_delivered = 0;
+        self.delivery_start = None;
+    }
+
+    /// The bytes acked per second between the first and the last counted acks, or `None` if they are not apart
+    pub fn bandwidth(&self) -> Option<f64> {
+        let elapsed = self.last_ack?.saturating_elapsed(self.delivery_start?);
+        if elapsed.is_zero() {
+            return None;
+        }
+        Some(self.bytes_delivered as f64 / elapsed.as_secs_f64())
+    }
 }
 
-/// Good -> bad: RTO exceeded
-/// Bad -> good: New RTT sample updated
+/// Good -> bad: RTO exceeded, `discredit_rtos` times in a row
+/// Bad -> good: New RTT sample updated, `credit_samples` times in a row
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Credit {
     Good,
     Bad,
 }
 
+/// `(fd, old credit, new credit)`
+pub type CreditTransition = (RawFd, Credit, Credit);
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
-pub enum AckSpace {
-    Payload { rtt: Option<time::Duration> },
-    Ping { now: time::Instant },
+pub enum AckSpace<T = time::Instant> {
+    Payload { rtt: Option<time::Duration>, now: T },
+    Ping { now: T },
+    Both { rtt: Option<time::Duration>, now: T },
+}
+
+impl<T> AckSpace<T>
+where
+    T: Timestamp,
+{
+    fn now(&self) -> T {
+        match self {
+            AckSpace::Payload { now, .. } | AckSpace::Ping { now } | AckSpace::Both { now, .. } => {
+                *now
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -313,19 +707,29 @@ pub enum ReassignPayloadError {
 
 pub type RetransmitPayloads = Vec<(RawFd, Seq16)>;
 
+pub(super) const DEFAULT_RTT_HISTORY: usize = 64;
+
+/// The outcome of `Sockets::reassign_rto_payloads`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RtoReassignment {
+    pub assigned: RetransmitPayloads,
+    /// Payloads still assigned to their old sockets, in the order given
+    pub unplaced: Vec<Seq16>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn ok() {
-        let mut sockets = Sockets::new();
+        let mut sockets: Sockets = Sockets::new();
         let fd1 = 1;
         let fd2 = 2;
         let fd3 = 3;
 
         sockets.add_fd(fd1);
-        sockets.remove_fd(fd1).unwrap();
+        sockets.remove_fd(fd1, &HashMap::new()).unwrap();
 
         sockets.add_fd(fd1);
         sockets.add_fd(fd2);
@@ -335,8 +739,8 @@ mod tests {
         let seq2 = Seq16::new(1);
 
         let now = time::Instant::now();
-        sockets.send_payload(fd1, seq1);
-        sockets.send_payload(fd2, seq2);
+        sockets.send_payload(fd1, seq1, now);
+        sockets.send_payload(fd2, seq2, now);
         let seq3 = sockets.send_ping(fd3, now).unwrap();
 
         assert_eq!(seq3, Seq16::new(0));
@@ -349,12 +753,13 @@ mod tests {
         sockets.discredit(seq2);
         sockets.discredit(seq3);
 
-        sockets.ack(fd1, seq1, AckSpace::Payload { rtt: None });
+        sockets.ack(fd1, seq1, AckSpace::Payload { rtt: None, now });
         sockets.ack(
             fd2,
             seq2,
             AckSpace::Payload {
                 rtt: Some(duration),
+                now,
             },
         );
         sockets.ack(fd3, seq3, AckSpace::Ping { now });
@@ -368,9 +773,56 @@ mod tests {
         assert_eq!(sockets.sockets[&fd3].credit, Credit::Good);
     }
 
+    #[test]
+    fn credit_hysteresis() {
+        let mut sockets: Sockets = Sockets::new();
+        sockets.set_credit_hysteresis(2, 2);
+        let fd = 1;
+        sockets.add_fd(fd);
+
+        let rtt = time::Duration::from_millis(100);
+        let mut now = time::Instant::now();
+        let sample = |sockets: &mut Sockets, now| {
+            let seq = sockets.send_ping(fd, now).unwrap();
+            sockets.ack(fd, seq, AckSpace::Ping { now: now + rtt });
+        };
+        let rto = |sockets: &mut Sockets, seq: u16, now| {
+            sockets.send_payload(fd, Seq16::new(seq), now);
+            sockets.discredit(Seq16::new(seq));
+        };
+
+        // Two samples to earn the credit
+        sample(&mut sockets, now);
+        assert_eq!(sockets.sockets[&fd].credit(), Credit::Bad);
+        now += rtt;
+        sample(&mut sockets, now);
+        assert_eq!(sockets.sockets[&fd].credit(), Credit::Good);
+
+        // A single RTO after a good sample does not discredit
+        now += rtt;
+        rto(&mut sockets, 0, now);
+        assert_eq!(sockets.sockets[&fd].credit(), Credit::Good);
+        now += rtt;
+        sample(&mut sockets, now);
+        now += rtt;
+        rto(&mut sockets, 1, now);
+        assert_eq!(sockets.sockets[&fd].credit(), Credit::Good);
+
+        // Two RTOs in a row do
+        rto(&mut sockets, 2, now);
+        assert_eq!(sockets.sockets[&fd].credit(), Credit::Bad);
+        assert_eq!(
+            sockets.take_credit_transitions(),
+            vec![
+                (fd, Credit::Bad, Credit::Good),
+                (fd, Credit::Good, Credit::Bad)
+            ]
+        );
+    }
+
     #[test]
     fn reassign_on_remove_fd() {
-        let mut sockets = Sockets::new();
+        let mut sockets: Sockets = Sockets::new();
         let fd1 = 1;
         let fd2 = 2;
         let fd3 = 3;
@@ -379,14 +831,15 @@ mod tests {
         sockets.add_fd(fd2);
         sockets.add_fd(fd3);
 
+        let now = time::Instant::now();
         let seq1 = Seq16::new(2);
-        sockets.send_payload(fd1, seq1);
+        sockets.send_payload(fd1, seq1, now);
         let seq1 = Seq16::new(3);
-        sockets.send_payload(fd1, seq1);
+        sockets.send_payload(fd1, seq1, now);
         let seq1 = Seq16::new(4);
-        sockets.send_payload(fd1, seq1);
+        sockets.send_payload(fd1, seq1, now);
 
-        let retx = sockets.remove_fd(fd1).unwrap();
+        let retx = sockets.remove_fd(fd1, &HashMap::new()).unwrap();
         let mut fd2_count = 0;
         let mut fd3_count = 0;
         let mut seqs = Vec::new();
@@ -412,7 +865,7 @@ mod tests {
 
     #[test]
     fn reassign_on_rto() {
-        let mut sockets = Sockets::new();
+        let mut sockets: Sockets = Sockets::new();
         let fd1 = 1;
         let fd2 = 2;
         let fd3 = 3;
@@ -426,25 +879,33 @@ mod tests {
         let seq2_1 = Seq16::new(2);
 
         let now = time::Instant::now();
-        sockets.send_payload(fd1, seq1_1);
-        sockets.send_payload(fd1, seq1_2);
-        sockets.send_payload(fd2, seq2_1);
+        sockets.send_payload(fd1, seq1_1, now);
+        sockets.send_payload(fd1, seq1_2, now);
+        sockets.send_payload(fd2, seq2_1, now);
 
         let duration = time::Duration::from_millis(100);
-        let _now = now + duration;
+        let now = now + duration;
 
         sockets.ack(
             fd2,
             seq2_1,
             AckSpace::Payload {
                 rtt: Some(duration),
+                now,
             },
         );
 
-        assert_eq!(sockets.reassign_rto_payloads(&[]).unwrap().len(), 0);
+        assert_eq!(
+            sockets.reassign_rto_payloads(&[], &HashMap::new()),
+            Ok(RtoReassignment::default())
+        );
 
         let retx_seqs = vec![seq1_1, seq1_2];
-        let retx = sockets.reassign_rto_payloads(&retx_seqs).unwrap();
+        let reassignment = sockets
+            .reassign_rto_payloads(&retx_seqs, &HashMap::new())
+            .unwrap();
+        assert!(reassignment.unplaced.is_empty());
+        let retx = reassignment.assigned;
 
         for (fd, seq) in retx {
             if fd != fd2 {
@@ -453,4 +914,141 @@ mod tests {
             assert!(seq == seq1_1 || seq == seq1_2);
         }
     }
+
+    #[test]
+    fn weighted_reassign() {
+        let mut sockets: Sockets = Sockets::new();
+        let fd1 = 1;
+        let fd2 = 2;
+        let fd3 = 3;
+
+        sockets.add_fd(fd1);
+        sockets.add_fd(fd2);
+        sockets.add_fd(fd3);
+
+        let now = time::Instant::now();
+        for i in 0..10 {
+            sockets.send_payload(fd1, Seq16::new(i), now);
+        }
+
+        let weights = HashMap::from_iter([(fd2, 0.9), (fd3, 0.1)]);
+        let retx = sockets.remove_fd(fd1, &weights).unwrap();
+        assert_eq!(retx.len(), 10);
+        let fd2_count = retx.iter().filter(|(fd, _)| *fd == fd2).count();
+        let fd3_count = retx.iter().filter(|(fd, _)| *fd == fd3).count();
+        assert_eq!(fd2_count, 9);
+        assert_eq!(fd3_count, 1);
+    }
+
+    #[test]
+    fn rto_unplaced() {
+        let mut sockets: Sockets = Sockets::with_rng(SplitMix64::new(0));
+        let fd1 = 1;
+        let fd2 = 2;
+        sockets.add_fd(fd1);
+        sockets.add_fd(fd2);
+        sockets.set_socket_window(Some(3));
+
+        let now = time::Instant::now();
+        for i in 0..4 {
+            sockets.send_payload(fd1, Seq16::new(i), now);
+        }
+        sockets.send_payload(fd2, Seq16::new(4), now);
+        sockets.send_payload(fd2, Seq16::new(5), now);
+        let rtt = time::Duration::from_millis(100);
+        sockets.ack(
+            fd2,
+            Seq16::new(5),
+            AckSpace::Payload {
+                rtt: Some(rtt),
+                now: now + rtt,
+            },
+        );
+
+        // `fd2` is the only credible socket and has room for two more
+        let rto_payloads = (0..4).map(Seq16::new).collect::<Vec<_>>();
+        let reassignment = sockets
+            .reassign_rto_payloads(&rto_payloads, &HashMap::new())
+            .unwrap();
+        assert_eq!(
+            reassignment.assigned,
+            vec![(fd2, Seq16::new(0)), (fd2, Seq16::new(1))]
+        );
+        assert_eq!(reassignment.unplaced, vec![Seq16::new(2), Seq16::new(3)]);
+        assert_eq!(sockets.payload_fd(Seq16::new(2)), Some(fd1));
+        assert_eq!(sockets.sockets()[&fd2].payloads().len(), 3);
+
+        // Full
+        let reassignment = sockets
+            .reassign_rto_payloads(&[Seq16::new(2)], &HashMap::new())
+            .unwrap();
+        assert!(reassignment.assigned.is_empty());
+        assert_eq!(reassignment.unplaced, vec![Seq16::new(2)]);
+
+        // No credible socket at all
+        assert_eq!(
+            sockets.reassign_rto_payloads(&[Seq16::new(4)], &HashMap::new()),
+            Err(ReassignPayloadError::NoSocketsLeft {
+                payloads: BTreeSet::from([Seq16::new(4)]),
+            })
+        );
+    }
+
+    #[test]
+    fn rtt_percentile() {
+        let mut sockets: Sockets = Sockets::new();
+        sockets.set_rtt_history(100);
+        let fd = 1;
+        sockets.add_fd(fd);
+        let now = time::Instant::now();
+        assert_eq!(sockets.sockets[&fd].rtt_percentile(0.5), None);
+
+        // 90 fast samples and a tail of 10 slow ones, shuffled
+        let mut samples = (0..100)
+            .map(|i| match i % 10 {
+                0 => time::Duration::from_millis(500 + i),
+                _ => time::Duration::from_millis(10 + i % 10),
+            })
+            .collect::<Vec<_>>();
+        samples.reverse();
+        for (i, rtt) in samples.into_iter().enumerate() {
+            let seq = Seq16::new(i as u16);
+            sockets.send_payload(fd, seq, now);
+            sockets.ack(
+                fd,
+                seq,
+                AckSpace::Payload {
+                    rtt: Some(rtt),
+                    now,
+                },
+            );
+        }
+
+        let socket = &sockets.sockets[&fd];
+        let p50 = socket.rtt_percentile(0.5).unwrap();
+        assert!(time::Duration::from_millis(10) < p50 && p50 < time::Duration::from_millis(20));
+        let p95 = socket.rtt_percentile(0.95).unwrap();
+        assert!(time::Duration::from_millis(500) <= p95);
+        assert_eq!(
+            socket.rtt_percentile(0.0),
+            Some(time::Duration::from_millis(11))
+        );
+        assert_eq!(
+            socket.rtt_percentile(1.0),
+            Some(time::Duration::from_millis(590))
+        );
+
+        // The oldest samples are overwritten
+        for i in 0..100 {
+            let seq = Seq16::new(100 + i);
+            sockets.send_payload(fd, seq, now);
+            let rtt = Some(time::Duration::from_millis(30));
+            sockets.ack(fd, seq, AckSpace::Payload { rtt, now });
+        }
+        let socket = &sockets.sockets[&fd];
+        assert_eq!(
+            socket.rtt_percentile(0.95),
+            Some(time::Duration::from_millis(30))
+        );
+    }
 }
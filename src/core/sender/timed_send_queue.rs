@@ -2,30 +2,43 @@ use std::{collections::BTreeMap, time};
 
 use seq::Seq16;
 
-use super::{RttStopwatch, SendQueue};
+use super::{RttStopwatch, SendQueue, SeqOrder, Timestamp};
 
-pub struct TimedSendQueue<K> {
-    rtt_stopwatches: BTreeMap<Seq16, KeyedRttStopwatch<K>>,
+pub struct TimedSendQueue<K, T = time::Instant> {
+    rtt_stopwatches: BTreeMap<Seq16, KeyedRttStopwatch<K, T>>,
     /// Packets that have been sent but not yet acknowledged
     send_queue: SendQueue,
 }
 
-impl<K> TimedSendQueue<K>
+impl<K, T> TimedSendQueue<K, T>
 where
     K: PartialEq,
+    T: Timestamp,
 {
     #[must_use]
     pub fn new(capacity: usize) -> Self {
+        Self::with_start(capacity, Seq16::new(0))
+    }
+
+    /// See `SendQueue::with_start`.
+    #[must_use]
+    pub fn with_start(capacity: usize, shadow_end: Seq16) -> Self {
         Self {
             rtt_stopwatches: BTreeMap::new(),
-            send_queue: SendQueue::new(capacity),
+            send_queue: SendQueue::with_start(capacity, shadow_end),
         }
     }
 
+    /// Drop all unacknowledged packets with their stopwatches and continue from `shadow_end`.
+    pub fn reset(&mut self, shadow_end: Seq16) {
+        self.rtt_stopwatches.clear();
+        self.send_queue.reset(shadow_end);
+    }
+
     pub fn retransmit(
         &mut self,
         seq: Seq16,
-        now: time::Instant,
+        now: T,
         timeout: time::Duration,
         key: K,
     ) -> Result<(), RetransmitError> {
@@ -47,13 +60,38 @@ where
         Ok(())
     }
 
-    pub fn rtt_stopwatch(&self, seq: Seq16) -> Option<&RttStopwatch> {
+    /// Restart the stopwatch of every timed out sequence with `key` and return the sequences.
+    pub fn retransmit_all_timed_out(
+        &mut self,
+        now: T,
+        timeout: time::Duration,
+        key: K,
+    ) -> Vec<Seq16>
+    where
+        K: Clone,
+    {
+        let mut sequences = Vec::new();
+        for (seq, rtt_stopwatch) in &mut self.rtt_stopwatches {
+            if !rtt_stopwatch.stopwatch.has_timed_out(now) {
+                continue;
+            }
+            *rtt_stopwatch = KeyedRttStopwatch {
+                stopwatch: RttStopwatch::new(now, timeout),
+                key: key.clone(),
+            };
+            sequences.push(*seq);
+        }
+
+        sequences
+    }
+
+    pub fn rtt_stopwatch(&self, seq: Seq16) -> Option<&RttStopwatch<T>> {
         self.rtt_stopwatches
             .get(&seq)
             .map(|stopwatch| &stopwatch.stopwatch)
     }
 
-    pub fn collect_timeout_sequences(&self, now: time::Instant) -> Vec<Seq16> {
+    pub fn collect_timeout_sequences(&self, now: T) -> Vec<Seq16> {
         // Collect all timed out sequences
         let mut sequences = Vec::new();
         for (seq, rtt_stopwatch) in &self.rtt_stopwatches {
@@ -65,7 +103,91 @@ where
         sequences
     }
 
-    pub fn send(&mut self, now: time::Instant, timeout: time::Duration, key: K) -> Option<Seq16> {
+    /// Same as `collect_timeout_sequences` but also returns the key each sequence was sent with.
+    pub fn collect_timeout_sequences_keyed(&self, now: T) -> Vec<(Seq16, &K)> {
+        // Collect all timed out sequences
+        let mut sequences = Vec::new();
+        for (seq, rtt_stopwatch) in &self.rtt_stopwatches {
+            if rtt_stopwatch.stopwatch.has_timed_out(now) {
+                sequences.push((*seq, &rtt_stopwatch.key));
+            }
+        }
+
+        sequences
+    }
+
+    /// The number of sequences sent but not yet acknowledged
+    pub fn pending_count(&self) -> usize {
+        self.rtt_stopwatches.len()
+    }
+
+    /// Return the key `seq` was last sent or retransmitted with.
+    pub fn peek_key(&self, seq: Seq16) -> Option<&K> {
+        self.rtt_stopwatches
+            .get(&seq)
+            .map(|rtt_stopwatch| &rtt_stopwatch.key)
+    }
+
+    /// The outstanding sequences along with their keys, in ascending order
+    pub fn keys(&self) -> impl Iterator<Item = (Seq16, &K)> {
+        self.rtt_stopwatches
+            .iter()
+            .map(|(seq, rtt_stopwatch)| (*seq, &rtt_stopwatch.key))
+    }
+
+    /// The sequences sent but not yet acknowledged, in ascending order
+    pub fn pending_sequences(&self) -> impl Iterator<Item = Seq16> + '_ {
+        self.rtt_stopwatches.keys().copied()
+    }
+
+    /// Return the unacknowledged sequence that has been waiting the longest, along with its elapsed time.
+    pub fn oldest_unacked(&self, now: T) -> Option<(Seq16, time::Duration)> {
+        self.rtt_stopwatches
+            .iter()
+            .min_by_key(|(_, rtt_stopwatch)| rtt_stopwatch.stopwatch.start())
+            .map(|(seq, rtt_stopwatch)| (*seq, rtt_stopwatch.stopwatch.elapsed(now)))
+    }
+
+    /// Return the most recently sent sequence that is not yet acknowledged.
+    pub fn newest_unacked(&self) -> Option<Seq16> {
+        self.rtt_stopwatches
+            .iter()
+            .max_by_key(|(_, rtt_stopwatch)| rtt_stopwatch.stopwatch.start())
+            .map(|(seq, _)| *seq)
+    }
+
+    /// Shrinking below `len` does not drop any outstanding sequence; `send` is rejected until enough of them are acknowledged.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.send_queue.set_capacity(capacity);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.send_queue.capacity()
+    }
+
+    /// The sequence number the next new packet takes
+    #[must_use]
+    pub fn next_seq(&self) -> Seq16 {
+        self.send_queue.next_seq()
+    }
+
+    /// The number of send window slots taken
+    ///
+    /// A paused sequence keeps its slot and a cancelled one frees it.
+    pub fn len(&self) -> usize {
+        self.send_queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.send_queue.is_empty()
+    }
+
+    /// The number of sequences that can be sent before the queue is full
+    pub fn remaining(&self) -> usize {
+        self.capacity().saturating_sub(self.len())
+    }
+
+    pub fn send(&mut self, now: T, timeout: time::Duration, key: K) -> Option<Seq16> {
         let Some(seq) = self.send_queue.send() else {
             return None;
         };
@@ -79,7 +201,85 @@ where
         Some(seq)
     }
 
-    pub fn ack(&mut self, seq: Seq16, now: time::Instant, key: K) -> Option<time::Duration> {
+    /// Ack every outstanding sequence strictly below `up_to` and return their RTTs in sequence order.
+    ///
+    /// Unlike `ack`, the RTT is returned whatever key the sequence was sent with.
+    pub fn ack_up_to(&mut self, up_to: Seq16, now: T) -> Vec<(Seq16, time::Duration)> {
+        let acked = self
+            .pending_sequences()
+            .filter(|seq| seq.is_before(up_to))
+            .collect::<Vec<_>>();
+        let mut rtts = Vec::new();
+        for seq in acked {
+            self.send_queue.ack(seq);
+            let Some(rtt_stopwatch) = self.rtt_stopwatches.remove(&seq) else {
+                unreachable!();
+            };
+            rtts.push((seq, rtt_stopwatch.stopwatch.into_rtt(now)));
+        }
+
+        rtts
+    }
+
+    /// Stop tracking `seq` without an RTT sample.
+    ///
+    /// Return `false` if `seq` is not outstanding.
+    pub fn cancel(&mut self, seq: Seq16) -> bool {
+        self.send_queue.ack(seq);
+        self.rtt_stopwatches.remove(&seq).is_some()
+    }
+
+    /// Cancel every outstanding sequence last sent or retransmitted with `key` and return the sequences in ascending order.
+    pub fn clear_key(&mut self, key: &K) -> Vec<Seq16> {
+        let cleared = self
+            .rtt_stopwatches
+            .iter()
+            .filter(|(_, rtt_stopwatch)| rtt_stopwatch.key == *key)
+            .map(|(seq, _)| *seq)
+            .collect::<Vec<_>>();
+        for seq in &cleared {
+            self.cancel(*seq);
+        }
+        cleared
+    }
+
+    /// Take the next sequence number without sending it, e.g. one already acked before a restore.
+    pub fn skip(&mut self) -> Option<Seq16> {
+        let seq = self.send_queue.send()?;
+        self.send_queue.ack(seq);
+        Some(seq)
+    }
+
+    /// Take the next sequence number and its send window slot without timing it, as if it were sent and paused, e.g. one orphaned before a restore.
+    pub fn reserve(&mut self) -> Option<Seq16> {
+        self.send_queue.send()
+    }
+
+    /// Stop the stopwatch of `seq` but keep its send window slot until it is acked.
+    ///
+    /// Return `false` if `seq` is not outstanding.
+    pub fn pause(&mut self, seq: Seq16) -> bool {
+        self.rtt_stopwatches.remove(&seq).is_some()
+    }
+
+    /// Start a new stopwatch for `seq` after it was paused.
+    ///
+    /// Return `false` if `seq` is still outstanding.
+    pub fn resume(&mut self, seq: Seq16, now: T, timeout: time::Duration, key: K) -> bool {
+        if self.rtt_stopwatches.contains_key(&seq) {
+            return false;
+        }
+        self.rtt_stopwatches.insert(
+            seq,
+            KeyedRttStopwatch {
+                stopwatch: RttStopwatch::new(now, timeout),
+                key,
+            },
+        );
+        true
+    }
+
+    pub fn ack(&mut self, seq: Seq16, now: T, key: K) -> Option<time::Duration> {
         self.send_queue.ack(seq);
         let Some(rtt_stopwatch) = self.rtt_stopwatches.remove(&seq) else {
             return None;
@@ -91,8 +291,8 @@ where
     }
 }
 
-struct KeyedRttStopwatch<K> {
-    stopwatch: RttStopwatch,
+struct KeyedRttStopwatch<K, T> {
+    stopwatch: RttStopwatch<T>,
     key: K,
 }
 
@@ -104,6 +304,7 @@ pub enum RetransmitError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::sender::{Clock, MockClock};
 
     #[test]
     fn ok() {
@@ -142,4 +343,337 @@ mod tests {
         let now = now + rtt;
         assert_eq!(queue.ack(Seq16::new(0), now, key_0), Some(rtt));
     }
+
+    #[test]
+    fn timeout_keyed() {
+        let mut queue = TimedSendQueue::new(10);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        let key_1 = 1;
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        let now = now + time::Duration::from_millis(50);
+        assert_eq!(queue.send(now, timeout, key_1), Some(Seq16::new(1)));
+        let now = now + time::Duration::from_millis(50);
+        assert_eq!(
+            queue.collect_timeout_sequences_keyed(now),
+            vec![(Seq16::new(0), &key_0)]
+        );
+        let now = now + time::Duration::from_millis(50);
+        assert_eq!(
+            queue.collect_timeout_sequences_keyed(now),
+            vec![(Seq16::new(0), &key_0), (Seq16::new(1), &key_1)]
+        );
+    }
+
+    #[test]
+    fn oldest_unacked() {
+        let mut queue = TimedSendQueue::new(10);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        assert_eq!(queue.pending_count(), 0);
+        assert_eq!(queue.oldest_unacked(now), None);
+
+        let start = now;
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        let now = now + time::Duration::from_millis(10);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(1)));
+        let now = now + time::Duration::from_millis(10);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(2)));
+        assert_eq!(queue.pending_count(), 3);
+
+        let now = now + time::Duration::from_millis(10);
+        assert_eq!(
+            queue.oldest_unacked(now),
+            Some((Seq16::new(0), now - start))
+        );
+
+        assert_eq!(queue.ack(Seq16::new(0), now, key_0), Some(now - start));
+        assert_eq!(queue.pending_count(), 2);
+        assert_eq!(
+            queue.oldest_unacked(now),
+            Some((Seq16::new(1), time::Duration::from_millis(20)))
+        );
+    }
+
+    #[test]
+    fn retransmit_all_timed_out() {
+        let mut queue = TimedSendQueue::new(10);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        let key_1 = 1;
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        let now = now + time::Duration::from_millis(30);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(1)));
+        let now = now + time::Duration::from_millis(30);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(2)));
+
+        // Only the first two sends have expired
+        let now = now + time::Duration::from_millis(70);
+        assert_eq!(
+            queue.retransmit_all_timed_out(now, timeout, key_1),
+            vec![Seq16::new(0), Seq16::new(1)]
+        );
+        assert_eq!(queue.collect_timeout_sequences(now), vec![]);
+
+        // The restarted stopwatches expire with the new key
+        let now = now + timeout;
+        assert_eq!(
+            queue.collect_timeout_sequences_keyed(now),
+            vec![
+                (Seq16::new(0), &key_1),
+                (Seq16::new(1), &key_1),
+                (Seq16::new(2), &key_0)
+            ]
+        );
+    }
+
+    #[test]
+    fn ack_up_to() {
+        let mut queue = TimedSendQueue::new(10);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        let key_1 = 1;
+        assert_eq!(queue.ack_up_to(Seq16::new(3), now), vec![]);
+
+        let start = now;
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        let now = now + time::Duration::from_millis(10);
+        assert_eq!(queue.send(now, timeout, key_1), Some(Seq16::new(1)));
+        let now = now + time::Duration::from_millis(10);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(2)));
+        let now = now + time::Duration::from_millis(10);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(3)));
+
+        let now = start + time::Duration::from_millis(50);
+        assert_eq!(
+            queue.ack_up_to(Seq16::new(3), now),
+            vec![
+                (Seq16::new(0), time::Duration::from_millis(50)),
+                (Seq16::new(1), time::Duration::from_millis(40)),
+                (Seq16::new(2), time::Duration::from_millis(30)),
+            ]
+        );
+        assert_eq!(queue.pending_count(), 1);
+        assert_eq!(queue.ack_up_to(Seq16::new(3), now), vec![]);
+
+        // Drain the queue
+        assert_eq!(
+            queue.ack_up_to(Seq16::new(4), now),
+            vec![(Seq16::new(3), time::Duration::from_millis(20))]
+        );
+        assert_eq!(queue.pending_count(), 0);
+        assert_eq!(queue.ack_up_to(Seq16::new(4), now), vec![]);
+    }
+
+    #[test]
+    fn peek_key() {
+        let mut queue = TimedSendQueue::new(10);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        let key_1 = 1;
+        assert_eq!(queue.peek_key(Seq16::new(0)), None);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        assert_eq!(queue.send(now, timeout, key_1), Some(Seq16::new(1)));
+        assert_eq!(queue.peek_key(Seq16::new(0)), Some(&key_0));
+        assert_eq!(queue.peek_key(Seq16::new(1)), Some(&key_1));
+        assert_eq!(
+            queue.keys().collect::<Vec<_>>(),
+            vec![(Seq16::new(0), &key_0), (Seq16::new(1), &key_1)]
+        );
+
+        // Retransmits replace the key
+        assert_eq!(queue.retransmit(Seq16::new(0), now, timeout, key_1), Ok(()));
+        assert_eq!(queue.peek_key(Seq16::new(0)), Some(&key_1));
+
+        assert_eq!(
+            queue.ack(Seq16::new(0), now, key_1),
+            Some(time::Duration::ZERO)
+        );
+        assert_eq!(queue.peek_key(Seq16::new(0)), None);
+        assert_eq!(
+            queue.keys().collect::<Vec<_>>(),
+            vec![(Seq16::new(1), &key_1)]
+        );
+    }
+
+    #[test]
+    fn cancel() {
+        let mut queue = TimedSendQueue::new(1);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        assert_eq!(queue.send(now, timeout, key_0), None);
+        assert!(queue.cancel(Seq16::new(0)));
+        assert!(!queue.cancel(Seq16::new(0)));
+        assert_eq!(queue.pending_count(), 0);
+        assert_eq!(queue.ack(Seq16::new(0), now, key_0), None);
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(1)));
+    }
+
+    #[test]
+    fn pause_resume() {
+        let mut queue = TimedSendQueue::new(2);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        assert_eq!(queue.send(now, timeout, key_0), Some(Seq16::new(0)));
+        assert!(queue.pause(Seq16::new(0)));
+        assert!(!queue.pause(Seq16::new(0)));
+        assert_eq!(queue.pending_count(), 0);
+        assert!(queue.collect_timeout_sequences(now + timeout).is_empty());
+
+        // The paused sequence keeps its slot
+        assert_eq!(queue.reserve(), Some(Seq16::new(1)));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.send(now, timeout, key_0), None);
+
+        let now = now + timeout;
+        assert!(queue.resume(Seq16::new(0), now, timeout, key_0));
+        assert!(!queue.resume(Seq16::new(0), now, timeout, key_0));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(
+            queue.ack(Seq16::new(0), now, key_0),
+            Some(time::Duration::ZERO)
+        );
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn mock_clock() {
+        let mut clock = MockClock::new();
+        let mut queue = TimedSendQueue::new(10);
+        let timeout = time::Duration::from_millis(100);
+        let key_0 = 0;
+        assert_eq!(queue.send(clock.now(), timeout, key_0), Some(Seq16::new(0)));
+        clock.advance(time::Duration::from_millis(99));
+        assert!(queue.collect_timeout_sequences(clock.now()).is_empty());
+        clock.advance(time::Duration::from_millis(1));
+        assert_eq!(
+            queue.collect_timeout_sequences(clock.now()),
+            vec![Seq16::new(0)]
+        );
+        assert_eq!(
+            queue.ack(Seq16::new(0), clock.now(), key_0),
+            Some(time::Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn set_capacity() {
+        let mut queue = TimedSendQueue::new(4);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        for _ in 0..3 {
+            assert!(queue.send(now, timeout, 0).is_some());
+        }
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.remaining(), 1);
+
+        // Shrink below the outstanding count
+        queue.set_capacity(2);
+        assert_eq!(queue.capacity(), 2);
+        assert_eq!(queue.remaining(), 0);
+        assert_eq!(queue.send(now, timeout, 0), None);
+        assert_eq!(queue.pending_count(), 3);
+        assert!(queue.rtt_stopwatch(Seq16::new(2)).is_some());
+        queue.ack(Seq16::new(0), now, 0);
+        assert_eq!(queue.send(now, timeout, 0), None);
+        queue.ack(Seq16::new(1), now, 0);
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(3)));
+        assert_eq!(queue.remaining(), 0);
+
+        // Grow
+        queue.set_capacity(5);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.remaining(), 3);
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(4)));
+    }
+
+    #[test]
+    fn with_start() {
+        let mut queue = TimedSendQueue::with_start(10, Seq16::new(40000));
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(40000)));
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(40001)));
+
+        // Stopwatches of the old sequences are gone
+        queue.reset(Seq16::new(7));
+        let now = now + timeout;
+        assert!(queue.collect_timeout_sequences(now).is_empty());
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(7)));
+    }
+
+    #[test]
+    fn clear_key() {
+        let mut queue = TimedSendQueue::new(3);
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(0)));
+        assert_eq!(queue.send(now, timeout, 1), Some(Seq16::new(1)));
+        assert_eq!(queue.send(now, timeout, 0), Some(Seq16::new(2)));
+        assert_eq!(queue.send(now, timeout, 1), None);
+
+        assert_eq!(queue.clear_key(&0), vec![Seq16::new(0), Seq16::new(2)]);
+        assert!(queue.clear_key(&0).is_empty());
+        assert_eq!(queue.len(), 1);
+
+        // The window is freed and the cleared sequences no longer time out
+        assert_eq!(queue.send(now, timeout, 1), Some(Seq16::new(3)));
+        let now = now + timeout;
+        assert_eq!(
+            queue.collect_timeout_sequences(now),
+            vec![Seq16::new(1), Seq16::new(3)]
+        );
+    }
+
+    #[test]
+    fn ticks() {
+        let mut queue = TimedSendQueue::<_, u64>::new(10);
+        let timeout = time::Duration::from_nanos(3);
+        assert_eq!(queue.send(0, timeout, 0), Some(Seq16::new(0)));
+        assert_eq!(queue.send(1, timeout, 0), Some(Seq16::new(1)));
+        assert!(queue.collect_timeout_sequences(2).is_empty());
+        assert_eq!(queue.collect_timeout_sequences(3), vec![Seq16::new(0)]);
+        assert_eq!(queue.oldest_unacked(3), Some((Seq16::new(0), timeout)));
+
+        assert_eq!(
+            queue.retransmit_all_timed_out(4, timeout, 1),
+            vec![Seq16::new(0), Seq16::new(1)]
+        );
+        assert_eq!(
+            queue.ack(Seq16::new(1), 5, 1),
+            Some(time::Duration::from_nanos(1))
+        );
+
+        // The tick counter went backwards
+        assert_eq!(queue.ack(Seq16::new(0), 2, 1), Some(time::Duration::ZERO));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn ack_up_to_across_wrap() {
+        let mut queue = TimedSendQueue::with_start(4, Seq16::new(65534));
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_millis(100);
+        for _ in 0..4 {
+            queue.send(now, timeout, 0).unwrap();
+        }
+        let acked = queue
+            .ack_up_to(Seq16::new(0), now)
+            .into_iter()
+            .map(|(seq, _)| seq)
+            .collect::<Vec<_>>();
+        assert_eq!(acked, vec![Seq16::new(65534), Seq16::new(65535)]);
+        assert_eq!(
+            queue.pending_sequences().collect::<Vec<_>>(),
+            vec![Seq16::new(0), Seq16::new(1)]
+        );
+    }
 }
@@ -0,0 +1,40 @@
+use std::{os::fd::RawFd, time};
+
+use super::{SchedulerInput, Socket, Timestamp};
+
+/// Turn the stats of a credible socket into the cost the scheduler minimizes.
+///
+/// Return `None` to leave the weight of the socket as it is, e.g. before any stats are in.
+pub trait Weigher<T = time::Instant> {
+    fn cost(&self, fd: RawFd, socket: &Socket<T>) -> Option<f64>;
+}
+
+/// Cost a socket by its smoothed RTT
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RttWeigher;
+
+impl<T> Weigher<T> for RttWeigher
+where
+    T: Timestamp,
+{
+    fn cost(&self, _fd: RawFd, socket: &Socket<T>) -> Option<f64> {
+        socket.rtt().map(|rtt| rtt.as_secs_f64())
+    }
+}
+
+impl<T> Weigher<T> for SchedulerInput
+where
+    T: Timestamp,
+{
+    fn cost(&self, fd: RawFd, socket: &Socket<T>) -> Option<f64> {
+        match self {
+            SchedulerInput::Rtt => RttWeigher.cost(fd, socket),
+            SchedulerInput::EffectiveRtt => socket.rtt().map(|rtt| {
+                // Each payload takes `1 / (1 - loss rate)` sends on average
+                let delivery_rate = f64::max(1.0 - socket.loss_rate(), f64::EPSILON);
+                rtt.as_secs_f64() / delivery_rate
+            }),
+            SchedulerInput::Bandwidth => socket.bandwidth().map(|bandwidth| 1.0 / bandwidth),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use seq::Seq16;
+
+/// Walk forward from `start` to `end` inclusively, wrapping past the end of the sequence space.
+pub fn seq_range(start: Seq16, end: Seq16) -> impl Iterator<Item = Seq16> {
+    let count = Seq16::dist(&start, &end) as usize + 1;
+    (0..count).map(move |i| start.add(i as u16))
+}
+
+/// The largest window that keeps `signed_dist` unambiguous, which is half the sequence space
+pub const MAX_WINDOW: usize = 1 << 15;
+
+/// Saturate `capacity` at `MAX_WINDOW`.
+///
+/// Larger windows wrap onto themselves, so sequences behind the window would look ahead of it.
+///
+/// # Panics
+///
+/// Panics in debug builds if `capacity` exceeds `MAX_WINDOW`.
+#[must_use]
+pub(crate) fn clamp_window(capacity: usize) -> usize {
+    debug_assert!(
+        capacity <= MAX_WINDOW,
+        "capacity {capacity} exceeds the max window {MAX_WINDOW}"
+    );
+    usize::min(capacity, MAX_WINDOW)
+}
+
+/// The shortest signed distance from `a` to `b`, in `-32768..=32767`.
+///
+/// It is positive if `b` is ahead of `a`, even across the wrap point.
+#[must_use]
+pub fn signed_dist(a: Seq16, b: Seq16) -> i32 {
+    Seq16::dist(&a, &b) as i16 as i32
+}
+
+/// Order sequences by `signed_dist` rather than by value, so `65535` comes before `0`.
+///
+/// Two sequences half the sequence space apart are each before the other, as `signed_dist` is negative both ways.
+pub trait SeqOrder {
+    fn is_before(&self, other: Seq16) -> bool;
+    fn is_after(&self, other: Seq16) -> bool;
+}
+
+impl SeqOrder for Seq16 {
+    fn is_before(&self, other: Seq16) -> bool {
+        signed_dist(other, *self) < 0
+    }
+
+    fn is_after(&self, other: Seq16) -> bool {
+        signed_dist(other, *self) > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let seqs = seq_range(Seq16::new(3), Seq16::new(6)).collect::<Vec<_>>();
+        assert_eq!(
+            seqs,
+            vec![Seq16::new(3), Seq16::new(4), Seq16::new(5), Seq16::new(6)]
+        );
+
+        let seqs = seq_range(Seq16::new(3), Seq16::new(3)).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![Seq16::new(3)]);
+    }
+
+    #[test]
+    fn signed_dist_wrap() {
+        assert_eq!(signed_dist(Seq16::new(65530), Seq16::new(5)), 11);
+        assert_eq!(signed_dist(Seq16::new(5), Seq16::new(65530)), -11);
+        assert_eq!(signed_dist(Seq16::new(7), Seq16::new(7)), 0);
+        assert_eq!(signed_dist(Seq16::new(0), Seq16::new(32767)), 32767);
+        assert_eq!(signed_dist(Seq16::new(0), Seq16::new(32768)), -32768);
+    }
+
+    #[test]
+    fn seq_order() {
+        assert!(Seq16::new(65535).is_before(Seq16::new(0)));
+        assert!(Seq16::new(0).is_after(Seq16::new(65535)));
+        assert!(Seq16::new(3).is_before(Seq16::new(4)));
+        assert!(!Seq16::new(4).is_before(Seq16::new(4)));
+        assert!(!Seq16::new(4).is_after(Seq16::new(4)));
+        assert!(Seq16::new(0).is_after(Seq16::new(32769)));
+
+        // Half the sequence space away is behind
+        assert!(Seq16::new(32768).is_before(Seq16::new(0)));
+        assert!(Seq16::new(0).is_before(Seq16::new(32768)));
+    }
+
+    #[test]
+    fn wrap() {
+        let seqs = seq_range(Seq16::new(65534), Seq16::new(1)).collect::<Vec<_>>();
+        assert_eq!(
+            seqs,
+            vec![
+                Seq16::new(65534),
+                Seq16::new(65535),
+                Seq16::new(0),
+                Seq16::new(1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use super::{DecodeError, Frame, FrameDecoder};
+
+/// Frames coalesced into one datagram.
+///
+/// # Format
+///
+/// ```text
+/// ( Frame, Frame, ... )
+/// ```
+///
+/// The frames are concatenated as is; each frame type tells where the frame ends.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameBatch(pub Vec<Frame>);
+
+impl FrameBatch {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Append `frame` unless the batch would then exceed `max_datagram_size` bytes once encoded.
+    ///
+    /// The frame is handed back if it does not fit, so the caller can start a new batch with it.
+    pub fn try_push(&mut self, frame: Frame, max_datagram_size: usize) -> Result<(), Frame> {
+        if self.encoded_len() + frame.encoded_len() > max_datagram_size {
+            return Err(frame);
+        }
+        self.0.push(frame);
+        Ok(())
+    }
+
+    /// The number of bytes the batch takes once encoded
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        self.0.iter().map(Frame::encoded_len).sum()
+    }
+
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.reserve(self.encoded_len());
+        for frame in &self.0 {
+            buf.extend::<Vec<u8>>(frame.into());
+        }
+    }
+
+    /// Decode frames until `buf` is consumed.
+    ///
+    /// A trailing partial frame fails the whole batch.
+    pub fn decode_all(buf: &[u8]) -> Result<Vec<Frame>, DecodeError> {
+        FrameDecoder::new(buf).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use seq::Seq16;
+
+    use super::*;
+    use crate::frame::{PayloadAck, Ping};
+
+    #[test]
+    fn ping_and_acks() {
+        let frames = vec![
+            Frame::Ping(Ping { seq: Seq16::new(0) }),
+            Frame::PayloadAck(PayloadAck { seq: Seq16::new(1) }),
+            Frame::PayloadAck(PayloadAck { seq: Seq16::new(2) }),
+        ];
+
+        // Each frame takes 3 bytes
+        let mut batch = FrameBatch::new();
+        for frame in frames.iter().cloned() {
+            batch.try_push(frame, 9).unwrap();
+        }
+        let extra = Frame::Ping(Ping { seq: Seq16::new(3) });
+        assert_eq!(batch.try_push(extra.clone(), 9), Err(extra));
+        assert_eq!(batch.encoded_len(), 9);
+
+        let mut buf = Vec::new();
+        batch.encode_into(&mut buf);
+        assert_eq!(buf.len(), 9);
+        assert_eq!(FrameBatch::decode_all(&buf), Ok(frames));
+
+        // Truncated
+        buf.pop();
+        assert_eq!(
+            FrameBatch::decode_all(&buf),
+            Err(DecodeError::InvalidPayloadAck)
+        );
+
+        assert_eq!(FrameBatch::decode_all(&[]), Ok(Vec::new()));
+    }
+}
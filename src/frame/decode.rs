@@ -1,3 +1,4 @@
+use std::io;
 use std::io::BufReader;
 use std::io::Read;
 
@@ -6,47 +7,158 @@ use byteorder::ReadBytesExt;
 use seq::Seq16;
 use thiserror::Error;
 
+use super::Ack;
 use super::Connect;
+use super::ConnectSecure;
+use super::ConnectV2;
 use super::Frame;
+use super::Hello;
+use super::Nack;
 use super::Payload;
 use super::PayloadAck;
+use super::PayloadAckEcn;
+use super::PayloadFragment;
 use super::Ping;
 use super::PingAck;
+use super::WindowUpdate;
+use crate::core::signed_dist;
 
 impl TryFrom<&mut BufReader<&[u8]>> for Frame {
     type Error = DecodeError;
 
     fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
-        let Ok(frame_type) = value.read_u8() else {
-            return Err(DecodeError::InvalidFrameType);
-        };
+        Frame::decode(value)
+    }
+}
+
+impl Frame {
+    /// Decode with the default options.
+    pub fn decode(value: &mut BufReader<&[u8]>) -> Result<Self, DecodeError> {
+        Frame::decode_with(value, &DecodeOptions::default())
+    }
+
+    /// Malformed input is reported as a `DecodeError` rather than a panic.
+    pub fn decode_with(
+        value: &mut BufReader<&[u8]>,
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let frame_type = value.read_u8()?;
+        Frame::decode_body(frame_type, value, options)
+    }
+
+    /// Decode the rest of a built-in frame after its frame type.
+    pub(crate) fn decode_body(
+        frame_type: u8,
+        value: &mut BufReader<&[u8]>,
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
         match frame_type {
-            0 => Ok(Frame::Payload(Payload::try_from(value)?)),
+            0 => Ok(Frame::Payload(Payload::decode_with(value, options, false)?)),
             1 => Ok(Frame::PayloadAck(PayloadAck::try_from(value)?)),
             2 => Ok(Frame::Ping(Ping::try_from(value)?)),
             3 => Ok(Frame::PingAck(PingAck::try_from(value)?)),
             4 => Ok(Frame::Connect(Connect::try_from(value)?)),
+            5 => Ok(Frame::Hello(Hello::try_from(value)?)),
+            6 => Ok(Frame::ConnectSecure(ConnectSecure::try_from(value)?)),
+            8 => Ok(Frame::Nack(Nack::try_from(value)?)),
+            9 => Ok(Frame::PayloadAckEcn(PayloadAckEcn::try_from(value)?)),
+            10 => Ok(Frame::ConnectV2(ConnectV2::try_from(value)?)),
+            11 => Ok(Frame::WindowUpdate(WindowUpdate::try_from(value)?)),
+            12 => Ok(Frame::PayloadFragment(PayloadFragment::decode_with(
+                value, options,
+            )?)),
+            13 => Ok(Frame::Ack(Ack::try_from(value)?)),
+            15 => Ok(Frame::Payload(Payload::decode_with(value, options, true)?)),
             _ => Err(DecodeError::InvalidFrameType),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Payloads and payload fragments with more data than this are rejected before allocation
+    pub max_payload_size: usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_payload_size: u16::MAX as usize,
+        }
+    }
+}
+
 impl TryFrom<&mut BufReader<&[u8]>> for Payload {
     type Error = DecodeError;
 
     fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        Payload::decode_with(value, &DecodeOptions::default(), false)
+    }
+}
+
+impl Payload {
+    fn decode_with(
+        value: &mut BufReader<&[u8]>,
+        options: &DecodeOptions,
+        retransmit: bool,
+    ) -> Result<Self, DecodeError> {
         let seq = parse_seq16(value, DecodeError::InvalidPayload)?;
-        let Ok(data_size) = value.read_u16::<BigEndian>() else {
-            return Err(DecodeError::InvalidPayload);
-        };
-        let mut data = vec![0; data_size as usize];
-        let Ok(()) = value.read_exact(&mut data) else {
-            return Err(DecodeError::InvalidPayload);
+        let data = parse_data(value, options, DecodeError::InvalidPayload)?;
+        Ok(Payload {
+            seq,
+            data,
+            retransmit,
+        })
+    }
+}
+
+impl PayloadFragment {
+    fn decode_with(
+        value: &mut BufReader<&[u8]>,
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let err = DecodeError::InvalidPayloadFragment;
+        let seq = parse_seq16(value, err)?;
+        let frag_index = value.read_u16::<BigEndian>().map_err(eof_as(err))?;
+        let last = match value.read_u8().map_err(eof_as(err))? {
+            0 => false,
+            1 => true,
+            _ => return Err(err),
         };
-        Ok(Payload { seq, data })
+        let data = parse_data(value, options, err)?;
+        Ok(PayloadFragment {
+            seq,
+            frag_index,
+            last,
+            data,
+        })
     }
 }
 
+/// Read the data size field and the data it declares.
+fn parse_data(
+    value: &mut BufReader<&[u8]>,
+    options: &DecodeOptions,
+    err: DecodeError,
+) -> Result<Vec<u8>, DecodeError> {
+    let data_size = value.read_u16::<BigEndian>().map_err(eof_as(err))?;
+    if data_size as usize > options.max_payload_size {
+        return Err(DecodeError::PayloadTooLarge);
+    }
+
+    // Only allocate for the data actually present
+    let mut data = Vec::new();
+    let read = value
+        .by_ref()
+        .take(data_size.into())
+        .read_to_end(&mut data)
+        .map_err(eof_as(err))?;
+    if read != data_size as usize {
+        return Err(err);
+    }
+    Ok(data)
+}
+
 impl TryFrom<&mut BufReader<&[u8]>> for PayloadAck {
     type Error = DecodeError;
 
@@ -78,29 +190,144 @@ impl TryFrom<&mut BufReader<&[u8]>> for PingAck {
 }
 
 fn parse_seq16(value: &mut BufReader<&[u8]>, err: DecodeError) -> Result<Seq16, DecodeError> {
-    let Ok(seq) = value.read_u16::<BigEndian>() else {
-        return Err(err);
-    };
+    let seq = value.read_u16::<BigEndian>().map_err(eof_as(err))?;
     Ok(Seq16::new(seq))
 }
 
+/// Only an input that ends at a frame boundary is a clean end, so running out inside a frame makes the frame malformed.
+fn eof_as(err: DecodeError) -> impl FnOnce(io::Error) -> DecodeError {
+    move |e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => err,
+        kind => DecodeError::Io(kind),
+    }
+}
+
 impl TryFrom<&mut BufReader<&[u8]>> for Connect {
     type Error = DecodeError;
 
     fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
-        let Ok(connection_id) = value.read_u32::<BigEndian>() else {
-            return Err(DecodeError::InvalidConnect);
-        };
-        Ok(Connect { connection_id })
+        let connection_id = value
+            .read_u32::<BigEndian>()
+            .map_err(eof_as(DecodeError::InvalidConnect))?;
+        Ok(Connect {
+            connection_id: connection_id.into(),
+        })
+    }
+}
+
+impl TryFrom<&mut BufReader<&[u8]>> for ConnectV2 {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        let Connect { connection_id } = Connect::try_from(&mut *value)?;
+        let initial_seq = parse_seq16(value, DecodeError::InvalidConnect)?;
+        let recv_window = value
+            .read_u16::<BigEndian>()
+            .map_err(eof_as(DecodeError::InvalidConnect))?;
+        Ok(ConnectV2 {
+            connection_id,
+            initial_seq,
+            recv_window,
+        })
+    }
+}
+
+impl TryFrom<&mut BufReader<&[u8]>> for ConnectSecure {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        let Connect { connection_id } = Connect::try_from(&mut *value)?;
+        let nonce = value
+            .read_u64::<BigEndian>()
+            .map_err(eof_as(DecodeError::InvalidConnect))?;
+        Ok(ConnectSecure {
+            connection_id,
+            nonce,
+        })
+    }
+}
+
+impl TryFrom<&mut BufReader<&[u8]>> for Ack {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        let cumulative = parse_seq16(value, DecodeError::InvalidAck)?;
+        let range_count = value.read_u8().map_err(eof_as(DecodeError::InvalidAck))?;
+        let mut ranges = Vec::with_capacity(range_count.into());
+        for _ in 0..range_count {
+            let start = parse_seq16(value, DecodeError::InvalidAck)?;
+            let end = parse_seq16(value, DecodeError::InvalidAck)?;
+            if signed_dist(start, end) < 0 {
+                return Err(DecodeError::InvalidAck);
+            }
+            ranges.push((start, end));
+        }
+        Ok(Ack { cumulative, ranges })
     }
 }
 
+impl TryFrom<&mut BufReader<&[u8]>> for Hello {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        let version = value.read_u8().map_err(eof_as(DecodeError::InvalidHello))?;
+        let capabilities = value
+            .read_u32::<BigEndian>()
+            .map_err(eof_as(DecodeError::InvalidHello))?;
+        Ok(Hello {
+            version,
+            capabilities,
+        })
+    }
+}
+
+impl TryFrom<&mut BufReader<&[u8]>> for Nack {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        Ok(Nack {
+            seq: parse_seq16(value, DecodeError::InvalidNack)?,
+        })
+    }
+}
+
+impl TryFrom<&mut BufReader<&[u8]>> for PayloadAckEcn {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        let seq = parse_seq16(value, DecodeError::InvalidPayloadAckEcn)?;
+        let ce_count = value
+            .read_u16::<BigEndian>()
+            .map_err(eof_as(DecodeError::InvalidPayloadAckEcn))?;
+        Ok(PayloadAckEcn { seq, ce_count })
+    }
+}
+
+impl TryFrom<&mut BufReader<&[u8]>> for WindowUpdate {
+    type Error = DecodeError;
+
+    fn try_from(value: &mut BufReader<&[u8]>) -> Result<Self, Self::Error> {
+        let available = value
+            .read_u16::<BigEndian>()
+            .map_err(eof_as(DecodeError::InvalidWindowUpdate))?;
+        Ok(WindowUpdate { available })
+    }
+}
+
+/// `UnexpectedEof` means the input ends at a frame boundary, so the caller may wait for more bytes.
+/// `Io` carries any other IO failure, and the other variants mean the input is malformed, including a frame cut short.
 #[derive(Debug, Clone, Copy, PartialEq, Error)]
 pub enum DecodeError {
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("IO error: {0:?}")]
+    Io(io::ErrorKind),
     #[error("invalid frame type")]
     InvalidFrameType,
     #[error("invalid payload")]
     InvalidPayload,
+    #[error("payload too large")]
+    PayloadTooLarge,
     #[error("invalid payload ack")]
     InvalidPayloadAck,
     #[error("invalid ping")]
@@ -109,4 +336,25 @@ pub enum DecodeError {
     InvalidPingAck,
     #[error("invalid connect")]
     InvalidConnect,
+    #[error("invalid hello")]
+    InvalidHello,
+    #[error("invalid nack")]
+    InvalidNack,
+    #[error("invalid payload ack with ECN")]
+    InvalidPayloadAckEcn,
+    #[error("invalid window update")]
+    InvalidWindowUpdate,
+    #[error("invalid payload fragment")]
+    InvalidPayloadFragment,
+    #[error("invalid ack")]
+    InvalidAck,
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => DecodeError::UnexpectedEof,
+            kind => DecodeError::Io(kind),
+        }
+    }
 }
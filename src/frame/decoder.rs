@@ -0,0 +1,96 @@
+use std::io::{BufRead, BufReader};
+
+use super::{DecodeError, Frame};
+
+/// Decode concatenated frames from a byte buffer.
+///
+/// A trailing partial frame yields one error and ends the iteration.
+pub struct FrameDecoder<'a> {
+    reader: BufReader<&'a [u8]>,
+    done: bool,
+}
+
+impl<'a> FrameDecoder<'a> {
+    #[must_use]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            reader: BufReader::new(buf),
+            done: false,
+        }
+    }
+}
+
+impl Iterator for FrameDecoder<'_> {
+    type Item = Result<Frame, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        // Stop at an exact frame boundary
+        let exhausted = match self.reader.fill_buf() {
+            Ok(buf) => buf.is_empty(),
+            Err(_) => true,
+        };
+        if exhausted {
+            self.done = true;
+            return None;
+        }
+
+        let res = Frame::decode(&mut self.reader);
+        if res.is_err() {
+            self.done = true;
+        }
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use seq::Seq16;
+
+    use super::*;
+    use crate::frame::{Ping, PingAck};
+
+    #[test]
+    fn empty() {
+        let mut decoder = FrameDecoder::new(&[]);
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn exact_frames() {
+        let mut buf = Vec::new();
+        for i in 0..3 {
+            let frame = Frame::Ping(Ping { seq: Seq16::new(i) });
+            buf.extend::<Vec<u8>>((&frame).into());
+        }
+        let mut decoder = FrameDecoder::new(&buf);
+        for i in 0..3 {
+            let Some(Ok(Frame::Ping(ping))) = decoder.next() else {
+                unreachable!();
+            };
+            assert_eq!(ping.seq, Seq16::new(i));
+        }
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let mut buf = Vec::new();
+        let frame = Frame::Ping(Ping { seq: Seq16::new(0) });
+        buf.extend::<Vec<u8>>((&frame).into());
+        let frame = Frame::PingAck(PingAck { seq: Seq16::new(1) });
+        buf.extend::<Vec<u8>>((&frame).into());
+        buf.pop();
+
+        let mut decoder = FrameDecoder::new(&buf);
+        assert!(matches!(decoder.next(), Some(Ok(Frame::Ping(_)))));
+        assert_eq!(
+            decoder.next().unwrap().err(),
+            Some(DecodeError::InvalidPingAck)
+        );
+        assert!(decoder.next().is_none());
+    }
+}
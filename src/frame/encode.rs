@@ -1,39 +1,104 @@
+use std::io::BufReader;
+
+use super::Ack;
 use super::Connect;
+use super::ConnectSecure;
+use super::ConnectV2;
 use super::Frame;
+use super::Hello;
+use super::Nack;
 use super::Payload;
 use super::PayloadAck;
+use super::PayloadAckEcn;
+use super::PayloadFragment;
 use super::Ping;
 use super::PingAck;
+use super::WindowUpdate;
 
 impl From<&Frame> for Vec<u8> {
     fn from(frame: &Frame) -> Self {
-        let mut buf = Vec::new();
+        let mut buf = Vec::with_capacity(frame.encoded_len());
+        buf.push(frame.frame_type());
         match frame {
-            Frame::Payload(payload) => {
-                buf.push(0);
-                buf.extend::<Vec<u8>>(payload.into());
-            }
-            Frame::PayloadAck(payload_ack) => {
-                buf.push(1);
-                buf.extend::<Vec<u8>>(payload_ack.into());
-            }
-            Frame::Ping(ping) => {
-                buf.push(2);
-                buf.extend::<Vec<u8>>(ping.into());
-            }
-            Frame::PingAck(ping_ack) => {
-                buf.push(3);
-                buf.extend::<Vec<u8>>(ping_ack.into());
-            }
-            Frame::Connect(connect) => {
-                buf.push(4);
-                buf.extend::<Vec<u8>>(connect.into());
+            Frame::Payload(payload) => buf.extend::<Vec<u8>>(payload.into()),
+            Frame::PayloadAck(payload_ack) => buf.extend::<Vec<u8>>(payload_ack.into()),
+            Frame::Ping(ping) => buf.extend::<Vec<u8>>(ping.into()),
+            Frame::PingAck(ping_ack) => buf.extend::<Vec<u8>>(ping_ack.into()),
+            Frame::Connect(connect) => buf.extend::<Vec<u8>>(connect.into()),
+            Frame::Hello(hello) => buf.extend::<Vec<u8>>(hello.into()),
+            Frame::Nack(nack) => buf.extend::<Vec<u8>>(nack.into()),
+            Frame::PayloadAckEcn(payload_ack_ecn) => buf.extend::<Vec<u8>>(payload_ack_ecn.into()),
+            Frame::ConnectV2(connect_v2) => buf.extend::<Vec<u8>>(connect_v2.into()),
+            Frame::WindowUpdate(window_update) => buf.extend::<Vec<u8>>(window_update.into()),
+            Frame::PayloadFragment(payload_fragment) => {
+                buf.extend::<Vec<u8>>(payload_fragment.into())
             }
+            Frame::ConnectSecure(connect_secure) => buf.extend::<Vec<u8>>(connect_secure.into()),
+            Frame::Ack(ack) => buf.extend::<Vec<u8>>(ack.into()),
+            Frame::Private(private) => buf.extend_from_slice(&private.body),
         }
         buf
     }
 }
 
+impl Frame {
+    #[must_use]
+    pub fn frame_type(&self) -> u8 {
+        match self {
+            Frame::Payload(payload) => match payload.retransmit {
+                true => 15,
+                false => 0,
+            },
+            Frame::PayloadAck(_) => 1,
+            Frame::Ping(_) => 2,
+            Frame::PingAck(_) => 3,
+            Frame::Connect(_) => 4,
+            Frame::Hello(_) => 5,
+            Frame::ConnectSecure(_) => 6,
+            Frame::Nack(_) => 8,
+            Frame::PayloadAckEcn(_) => 9,
+            Frame::ConnectV2(_) => 10,
+            Frame::WindowUpdate(_) => 11,
+            Frame::PayloadFragment(_) => 12,
+            Frame::Ack(_) => 13,
+            Frame::Private(private) => private.frame_type,
+        }
+    }
+
+    /// Return `true` if the frame decodes back to itself once encoded.
+    #[must_use]
+    pub fn roundtrip_ok(&self) -> bool {
+        let buf: Vec<u8> = self.into();
+        let mut reader = BufReader::new(&buf[..]);
+        match Frame::decode(&mut reader) {
+            Ok(frame) => frame == *self,
+            Err(_) => false,
+        }
+    }
+
+    /// The number of bytes the frame takes once encoded, including the frame type.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        let body_len = match self {
+            Frame::Payload(payload) => 2 + 2 + payload.data.len(),
+            Frame::PayloadAck(_) => 2,
+            Frame::Ping(_) => 2,
+            Frame::PingAck(_) => 2,
+            Frame::Connect(_) => 4,
+            Frame::Hello(_) => 1 + 4,
+            Frame::Nack(_) => 2,
+            Frame::PayloadAckEcn(_) => 2 + 2,
+            Frame::ConnectV2(_) => 4 + 2 + 2,
+            Frame::WindowUpdate(_) => 2,
+            Frame::PayloadFragment(payload_fragment) => 2 + 2 + 1 + 2 + payload_fragment.data.len(),
+            Frame::ConnectSecure(_) => 4 + 8,
+            Frame::Ack(ack) => 2 + 1 + (2 + 2) * ack.ranges.len(),
+            Frame::Private(private) => private.body.len(),
+        };
+        1 + body_len
+    }
+}
+
 impl From<&Payload> for Vec<u8> {
     fn from(payload: &Payload) -> Self {
         let mut buf = Vec::new();
@@ -71,7 +136,85 @@ impl From<&PingAck> for Vec<u8> {
 impl From<&Connect> for Vec<u8> {
     fn from(connect: &Connect) -> Self {
         let mut buf = Vec::new();
-        buf.extend_from_slice(&connect.connection_id.to_be_bytes());
+        buf.extend_from_slice(&connect.connection_id.value().to_be_bytes());
+        buf
+    }
+}
+
+impl From<&ConnectV2> for Vec<u8> {
+    fn from(connect_v2: &ConnectV2) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&connect_v2.connection_id.value().to_be_bytes());
+        buf.extend_from_slice(&connect_v2.initial_seq.value().to_be_bytes());
+        buf.extend_from_slice(&connect_v2.recv_window.to_be_bytes());
+        buf
+    }
+}
+
+impl From<&ConnectSecure> for Vec<u8> {
+    fn from(connect_secure: &ConnectSecure) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&connect_secure.connection_id.value().to_be_bytes());
+        buf.extend_from_slice(&connect_secure.nonce.to_be_bytes());
+        buf
+    }
+}
+
+impl From<&Ack> for Vec<u8> {
+    fn from(ack: &Ack) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&ack.cumulative.value().to_be_bytes());
+        buf.push(ack.ranges.len() as u8);
+        for (start, end) in &ack.ranges {
+            buf.extend_from_slice(&start.value().to_be_bytes());
+            buf.extend_from_slice(&end.value().to_be_bytes());
+        }
+        buf
+    }
+}
+
+impl From<&Hello> for Vec<u8> {
+    fn from(hello: &Hello) -> Self {
+        let mut buf = Vec::new();
+        buf.push(hello.version);
+        buf.extend_from_slice(&hello.capabilities.to_be_bytes());
+        buf
+    }
+}
+
+impl From<&Nack> for Vec<u8> {
+    fn from(nack: &Nack) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&nack.seq.value().to_be_bytes());
+        buf
+    }
+}
+
+impl From<&PayloadAckEcn> for Vec<u8> {
+    fn from(payload_ack_ecn: &PayloadAckEcn) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&payload_ack_ecn.seq.value().to_be_bytes());
+        buf.extend_from_slice(&payload_ack_ecn.ce_count.to_be_bytes());
+        buf
+    }
+}
+
+impl From<&WindowUpdate> for Vec<u8> {
+    fn from(window_update: &WindowUpdate) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&window_update.available.to_be_bytes());
+        buf
+    }
+}
+
+impl From<&PayloadFragment> for Vec<u8> {
+    fn from(payload_fragment: &PayloadFragment) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&payload_fragment.seq.value().to_be_bytes());
+        buf.extend_from_slice(&payload_fragment.frag_index.to_be_bytes());
+        buf.push(payload_fragment.last.into());
+        buf.extend_from_slice(&(payload_fragment.data.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&payload_fragment.data);
         buf
     }
 }
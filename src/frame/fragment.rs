@@ -0,0 +1,325 @@
+use std::{collections::BTreeMap, time};
+
+use seq::Seq16;
+use thiserror::Error;
+
+use super::PayloadFragment;
+use crate::core::Timestamp;
+
+/// Split messages too large for a single `Payload` into `PayloadFragment`s.
+pub struct Fragmenter {
+    max_fragment_size: usize,
+}
+
+impl Fragmenter {
+    /// `max_fragment_size` is capped to what the data size field can hold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_fragment_size` is zero.
+    #[must_use]
+    pub fn new(max_fragment_size: usize) -> Self {
+        assert!(max_fragment_size > 0);
+        Self {
+            max_fragment_size: usize::min(max_fragment_size, u16::MAX as usize),
+        }
+    }
+
+    /// The fragments take consecutive sequences starting from `first_seq`.
+    ///
+    /// An empty message still takes one fragment.
+    pub fn fragment(
+        &self,
+        first_seq: Seq16,
+        data: &[u8],
+    ) -> Result<Vec<PayloadFragment>, FragmentError> {
+        let chunks = data.chunks(self.max_fragment_size).collect::<Vec<_>>();
+        let chunks = match chunks.is_empty() {
+            true => vec![&data[..0]],
+            false => chunks,
+        };
+        if chunks.len() > u16::MAX as usize {
+            return Err(FragmentError::TooManyFragments);
+        }
+
+        let last_index = chunks.len() - 1;
+        let fragments = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| PayloadFragment {
+                seq: first_seq.add(i as u16),
+                frag_index: i as u16,
+                last: i == last_index,
+                data: chunk.to_vec(),
+            })
+            .collect();
+        Ok(fragments)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum FragmentError {
+    #[error("too many fragments")]
+    TooManyFragments,
+}
+
+/// Rebuild the messages split by a `Fragmenter`.
+///
+/// At most `max_partials` messages wait for their missing fragments, the oldest being dropped first.
+/// A partial message is dropped once its fragments take more than `max_message_size` bytes.
+pub struct Reassembler<T = time::Instant> {
+    /// Partial messages keyed by the sequence of their first fragment
+    partials: BTreeMap<Seq16, Partial<T>>,
+    /// How long a partial message waits for its missing fragments
+    timeout: time::Duration,
+    max_partials: usize,
+    max_message_size: usize,
+}
+
+impl<T> Reassembler<T>
+where
+    T: Timestamp,
+{
+    /// # Panics
+    ///
+    /// Panics if `max_partials` is zero.
+    #[must_use]
+    pub fn new(timeout: time::Duration, max_partials: usize, max_message_size: usize) -> Self {
+        assert!(max_partials > 0);
+        Self {
+            partials: BTreeMap::new(),
+            timeout,
+            max_partials,
+            max_message_size,
+        }
+    }
+
+    /// Return the whole message once its last missing fragment arrives.
+    ///
+    /// Fragments past the last fragment of their message are dropped.
+    #[must_use]
+    pub fn push(&mut self, now: T, fragment: PayloadFragment) -> Option<Vec<u8>> {
+        let first_seq = Seq16::new(fragment.seq.value().wrapping_sub(fragment.frag_index));
+
+        // Drop the oldest partial message
+        if !self.partials.contains_key(&first_seq) && self.partials.len() == self.max_partials {
+            if let Some(oldest) = self
+                .partials
+                .iter()
+                .min_by_key(|(_, partial)| partial.start)
+                .map(|(first_seq, _)| *first_seq)
+            {
+                self.partials.remove(&oldest);
+            }
+        }
+
+        let partial = self.partials.entry(first_seq).or_insert_with(|| Partial {
+            fragments: BTreeMap::new(),
+            size: 0,
+            last_index: None,
+            start: now,
+        });
+        match (partial.last_index, fragment.last) {
+            (Some(last_index), _) if fragment.frag_index > last_index => {
+                // Past the last fragment
+                return None;
+            }
+            (Some(last_index), true) if fragment.frag_index != last_index => {
+                // Conflicts with the last fragment
+                return None;
+            }
+            (None, true) => {
+                // Forget the fragments past the last one
+                partial
+                    .fragments
+                    .retain(|frag_index, _| *frag_index <= fragment.frag_index);
+                partial.size = partial.fragments.values().map(Vec::len).sum();
+                partial.last_index = Some(fragment.frag_index);
+            }
+            _ => (),
+        }
+        partial.size += fragment.data.len();
+        if let Some(old) = partial.fragments.insert(fragment.frag_index, fragment.data) {
+            partial.size -= old.len();
+        }
+
+        // Drop the partial message over the budget
+        if partial.size > self.max_message_size {
+            self.partials.remove(&first_seq);
+            return None;
+        }
+
+        // Wait for the missing fragments
+        let last_index = partial.last_index?;
+        if partial.fragments.len() != last_index as usize + 1
+            || !partial.fragments.keys().copied().eq(0..=last_index)
+        {
+            return None;
+        }
+
+        let partial = self.partials.remove(&first_seq).unwrap();
+        Some(partial.fragments.into_values().flatten().collect())
+    }
+
+    /// Drop the partial messages that have waited for `timeout` and return the sequences of their first fragments.
+    pub fn expire(&mut self, now: T) -> Vec<Seq16> {
+        let expired = self
+            .partials
+            .iter()
+            .filter(|(_, partial)| now.saturating_elapsed(partial.start) >= self.timeout)
+            .map(|(first_seq, _)| *first_seq)
+            .collect::<Vec<_>>();
+        for first_seq in &expired {
+            self.partials.remove(first_seq);
+        }
+        expired
+    }
+
+    /// The number of messages still missing fragments
+    #[must_use]
+    pub fn pending_count(&self) -> usize {
+        self.partials.len()
+    }
+}
+
+struct Partial<T> {
+    /// Fragment data keyed by fragment index
+    fragments: BTreeMap<u16, Vec<u8>>,
+    /// The number of bytes in `fragments`
+    size: usize,
+    /// The fragment index of the last fragment, once received
+    last_index: Option<u16>,
+    /// When the first fragment arrived
+    start: T,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::frame::Frame;
+
+    #[test]
+    fn jumbo() {
+        let data = (0..200 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+        let fragmenter = Fragmenter::new(1200);
+        let fragments = fragmenter
+            .fragment(Seq16::new(u16::MAX - 1), &data)
+            .unwrap();
+        assert_eq!(fragments.len(), 171);
+        assert!(fragments.last().unwrap().last);
+
+        // Go over the wire in reverse order
+        let mut reassembler = Reassembler::new(time::Duration::from_secs(1), 1, data.len());
+        let now = time::Instant::now();
+        let mut message = None;
+        for fragment in fragments.into_iter().rev() {
+            let buf: Vec<u8> = (&Frame::PayloadFragment(fragment)).into();
+            let mut reader = BufReader::new(&buf[..]);
+            let Frame::PayloadFragment(fragment) = Frame::decode(&mut reader).unwrap() else {
+                unreachable!();
+            };
+            assert!(message.is_none());
+            message = reassembler.push(now, fragment);
+        }
+        assert_eq!(message.unwrap(), data);
+        assert_eq!(reassembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn missing_fragment() {
+        let fragmenter = Fragmenter::new(2);
+        let mut fragments = fragmenter
+            .fragment(Seq16::new(10), &[1, 2, 3, 4, 5])
+            .unwrap();
+        assert_eq!(fragments.len(), 3);
+        fragments.remove(1);
+
+        let mut reassembler = Reassembler::new(time::Duration::from_secs(1), 1, 1024);
+        let now = time::Instant::now();
+        for fragment in fragments {
+            assert!(reassembler.push(now, fragment).is_none());
+        }
+        assert_eq!(reassembler.pending_count(), 1);
+
+        assert!(reassembler.expire(now).is_empty());
+        let now = now + time::Duration::from_secs(1);
+        assert_eq!(reassembler.expire(now), vec![Seq16::new(10)]);
+        assert_eq!(reassembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn fragment_past_last() {
+        let fragment = |frag_index: u16, last: bool| PayloadFragment {
+            seq: Seq16::new(10 + frag_index),
+            frag_index,
+            last,
+            data: vec![frag_index as u8],
+        };
+        let mut reassembler = Reassembler::new(time::Duration::from_secs(1), 1, 1024);
+        let now = time::Instant::now();
+
+        // Fragment 5 is dropped rather than standing in for the missing fragment 0
+        assert!(reassembler.push(now, fragment(1, true)).is_none());
+        assert!(reassembler.push(now, fragment(5, false)).is_none());
+        assert_eq!(reassembler.push(now, fragment(0, false)), Some(vec![0, 1]));
+
+        // Fragments received before the last one are forgotten once past it
+        assert!(reassembler.push(now, fragment(5, false)).is_none());
+        assert!(reassembler.push(now, fragment(1, true)).is_none());
+        assert_eq!(reassembler.push(now, fragment(0, false)), Some(vec![0, 1]));
+        assert_eq!(reassembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn max_partials() {
+        let fragmenter = Fragmenter::new(1);
+        let mut reassembler = Reassembler::new(time::Duration::from_secs(1), 2, 1024);
+        let start = time::Instant::now();
+        let mut tails = Vec::new();
+        for (i, first_seq) in [0, 10, 20].into_iter().enumerate() {
+            let now = start + time::Duration::from_millis(i as u64);
+            let fragments = fragmenter.fragment(Seq16::new(first_seq), &[1, 2]).unwrap();
+            let mut fragments = fragments.into_iter();
+            assert!(reassembler.push(now, fragments.next().unwrap()).is_none());
+            tails.push(fragments.next().unwrap());
+        }
+        assert_eq!(reassembler.pending_count(), 2);
+
+        // The oldest partial message is dropped
+        let now = start + time::Duration::from_millis(3);
+        let mut tails = tails.into_iter();
+        let oldest = tails.next().unwrap();
+        for fragment in tails {
+            assert_eq!(reassembler.push(now, fragment), Some(vec![1, 2]));
+        }
+        assert!(reassembler.push(now, oldest).is_none());
+    }
+
+    #[test]
+    fn max_message_size() {
+        let fragmenter = Fragmenter::new(2);
+        let mut reassembler = Reassembler::new(time::Duration::from_secs(1), 1, 4);
+        let now = time::Instant::now();
+
+        // A duplicate fragment does not count twice
+        let fragments = fragmenter.fragment(Seq16::new(0), &[1, 2, 3, 4]).unwrap();
+        assert!(reassembler.push(now, fragments[0].clone()).is_none());
+        assert!(reassembler.push(now, fragments[0].clone()).is_none());
+        assert_eq!(
+            reassembler.push(now, fragments[1].clone()),
+            Some(vec![1, 2, 3, 4])
+        );
+
+        // The partial message is dropped once over the budget
+        let fragments = fragmenter
+            .fragment(Seq16::new(10), &[1, 2, 3, 4, 5])
+            .unwrap();
+        assert!(reassembler.push(now, fragments[0].clone()).is_none());
+        assert!(reassembler.push(now, fragments[1].clone()).is_none());
+        assert_eq!(reassembler.pending_count(), 1);
+        assert!(reassembler.push(now, fragments[2].clone()).is_none());
+        assert_eq!(reassembler.pending_count(), 0);
+    }
+}
@@ -0,0 +1,184 @@
+use super::Frame;
+
+/// One field of a frame in the order it is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    /// The number of bytes the field takes, or `None` if it is as long as the preceding size or count field says
+    pub width: Option<usize>,
+    pub endianness: Endianness,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+const fn fixed(name: &'static str, width: usize) -> FieldSpec {
+    FieldSpec {
+        name,
+        width: Some(width),
+        endianness: Endianness::Big,
+    }
+}
+
+const fn sized(name: &'static str) -> FieldSpec {
+    FieldSpec {
+        name,
+        width: None,
+        endianness: Endianness::Big,
+    }
+}
+
+const FRAME_TYPE: FieldSpec = fixed("Frame type", 1);
+const SEQ: FieldSpec = fixed("Seq", 2);
+const CONNECTION_ID: FieldSpec = fixed("Connection ID", 4);
+const DATA_SIZE: FieldSpec = fixed("Data size", 2);
+const DATA: FieldSpec = sized("Data");
+
+const PAYLOAD: [FieldSpec; 4] = [FRAME_TYPE, SEQ, DATA_SIZE, DATA];
+const SEQ_ONLY: [FieldSpec; 2] = [FRAME_TYPE, SEQ];
+const CONNECT: [FieldSpec; 2] = [FRAME_TYPE, CONNECTION_ID];
+const HELLO: [FieldSpec; 3] = [FRAME_TYPE, fixed("Version", 1), fixed("Capabilities", 4)];
+const PAYLOAD_ACK_ECN: [FieldSpec; 3] = [FRAME_TYPE, SEQ, fixed("CE count", 2)];
+const CONNECT_V2: [FieldSpec; 4] = [
+    FRAME_TYPE,
+    CONNECTION_ID,
+    fixed("Initial seq", 2),
+    fixed("Receive window", 2),
+];
+const WINDOW_UPDATE: [FieldSpec; 2] = [FRAME_TYPE, fixed("Available", 2)];
+const PAYLOAD_FRAGMENT: [FieldSpec; 6] = [
+    FRAME_TYPE,
+    SEQ,
+    fixed("Fragment index", 2),
+    fixed("Last", 1),
+    DATA_SIZE,
+    DATA,
+];
+const CONNECT_SECURE: [FieldSpec; 3] = [FRAME_TYPE, CONNECTION_ID, fixed("Nonce", 8)];
+/// Each range takes a start and an end of 2 bytes each
+const RANGE_COUNT: FieldSpec = fixed("Range count", 1);
+const ACK: [FieldSpec; 4] = [
+    FRAME_TYPE,
+    fixed("Cumulative", 2),
+    RANGE_COUNT,
+    sized("Ranges"),
+];
+
+impl Frame {
+    /// The fields of the built-in frame type `frame_type`, starting from the frame type itself.
+    ///
+    /// This is the same format as the `# Format` section of each frame.
+    #[must_use]
+    pub fn layout(frame_type: u8) -> Option<&'static [FieldSpec]> {
+        let layout: &[FieldSpec] = match frame_type {
+            0 | 15 => &PAYLOAD,
+            1..=3 | 8 => &SEQ_ONLY,
+            4 => &CONNECT,
+            5 => &HELLO,
+            6 => &CONNECT_SECURE,
+            9 => &PAYLOAD_ACK_ECN,
+            10 => &CONNECT_V2,
+            11 => &WINDOW_UPDATE,
+            12 => &PAYLOAD_FRAGMENT,
+            13 => &ACK,
+            _ => return None,
+        };
+        Some(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use seq::Seq16;
+
+    use super::*;
+    use crate::frame::{
+        Ack, Connect, ConnectSecure, ConnectV2, Hello, Nack, Payload, PayloadAck, PayloadAckEcn,
+        PayloadFragment, Ping, PingAck, PrivateFrame, WindowUpdate,
+    };
+
+    #[test]
+    fn layout_matches_encoder() {
+        let frames = vec![
+            Frame::Payload(Payload {
+                seq: Seq16::new(0),
+                data: vec![1, 2, 3],
+                retransmit: false,
+            }),
+            Frame::PayloadAck(PayloadAck { seq: Seq16::new(1) }),
+            Frame::Ping(Ping { seq: Seq16::new(2) }),
+            Frame::PingAck(PingAck { seq: Seq16::new(3) }),
+            Frame::Connect(Connect {
+                connection_id: 4.into(),
+            }),
+            Frame::Hello(Hello {
+                version: 5,
+                capabilities: 6,
+            }),
+            Frame::Nack(Nack { seq: Seq16::new(7) }),
+            Frame::PayloadAckEcn(PayloadAckEcn {
+                seq: Seq16::new(8),
+                ce_count: 9,
+            }),
+            Frame::ConnectV2(ConnectV2 {
+                connection_id: 10.into(),
+                initial_seq: Seq16::new(11),
+                recv_window: 12,
+            }),
+            Frame::WindowUpdate(WindowUpdate { available: 13 }),
+            Frame::PayloadFragment(PayloadFragment {
+                seq: Seq16::new(14),
+                frag_index: 15,
+                last: true,
+                data: vec![16, 17],
+            }),
+            Frame::ConnectSecure(ConnectSecure {
+                connection_id: 18.into(),
+                nonce: 19,
+            }),
+            Frame::Ack(Ack {
+                cumulative: Seq16::new(20),
+                ranges: vec![(Seq16::new(22), Seq16::new(23))],
+            }),
+            Frame::Payload(Payload {
+                seq: Seq16::new(24),
+                data: vec![25],
+                retransmit: true,
+            }),
+        ];
+        for frame in frames {
+            let buf: Vec<u8> = (&frame).into();
+            let layout = Frame::layout(frame.frame_type()).unwrap();
+            assert_eq!(layout[0], FRAME_TYPE);
+
+            // Walk the encoded frame field by field
+            let mut pos = 0;
+            let mut size = None;
+            for field in layout {
+                let width = match field.width {
+                    Some(width) => width,
+                    None => size.take().unwrap(),
+                };
+                let bytes = &buf[pos..pos + width];
+                if *field == DATA_SIZE {
+                    size = Some(u16::from_be_bytes([bytes[0], bytes[1]]) as usize);
+                }
+                if *field == RANGE_COUNT {
+                    size = Some(bytes[0] as usize * (2 + 2));
+                }
+                pos += width;
+            }
+            assert_eq!(pos, buf.len(), "{frame:?}");
+        }
+
+        assert_eq!(Frame::layout(7), None);
+        let private = Frame::Private(PrivateFrame {
+            frame_type: 240,
+            body: vec![],
+        });
+        assert_eq!(Frame::layout(private.frame_type()), None);
+    }
+}
@@ -1,9 +1,25 @@
+mod batch;
 mod decode;
+mod decoder;
 mod encode;
+mod fragment;
+mod layout;
+mod registry;
+mod sack;
+#[cfg(feature = "serde")]
+pub(crate) mod seq16_serde;
+mod stream_parser;
 
+pub use batch::*;
 pub use decode::*;
+pub use decoder::*;
 pub use encode::*;
+pub use fragment::*;
+pub use layout::*;
+pub use registry::*;
+pub use sack::*;
 use seq::Seq16;
+pub use stream_parser::*;
 
 /// # Format
 ///
@@ -12,12 +28,63 @@ use seq::Seq16;
 /// ```
 ///
 /// - Frame type field length: `u8`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Frame {
     Payload(Payload),
     PayloadAck(PayloadAck),
     Ping(Ping),
     PingAck(PingAck),
     Connect(Connect),
+    Hello(Hello),
+    Nack(Nack),
+    PayloadAckEcn(PayloadAckEcn),
+    ConnectV2(ConnectV2),
+    WindowUpdate(WindowUpdate),
+    PayloadFragment(PayloadFragment),
+    ConnectSecure(ConnectSecure),
+    Ack(Ack),
+    Private(PrivateFrame),
+}
+
+impl Frame {
+    /// Return `true` for `PayloadAck`, `PingAck`, `PayloadAckEcn`, and `Ack`.
+    #[must_use]
+    pub fn is_ack(&self) -> bool {
+        matches!(
+            self,
+            Frame::PayloadAck(_) | Frame::PingAck(_) | Frame::PayloadAckEcn(_) | Frame::Ack(_)
+        )
+    }
+
+    /// Return `true` for `Payload` and `PayloadFragment`.
+    #[must_use]
+    pub fn is_data(&self) -> bool {
+        matches!(self, Frame::Payload(_) | Frame::PayloadFragment(_))
+    }
+
+    /// The sequence the frame references.
+    ///
+    /// `None` for connection-level frames and for `Ack`, which references a set of sequences.
+    #[must_use]
+    pub fn seq(&self) -> Option<Seq16> {
+        match self {
+            Frame::Payload(Payload { seq, .. })
+            | Frame::PayloadAck(PayloadAck { seq })
+            | Frame::Ping(Ping { seq })
+            | Frame::PingAck(PingAck { seq })
+            | Frame::Nack(Nack { seq })
+            | Frame::PayloadAckEcn(PayloadAckEcn { seq, .. })
+            | Frame::PayloadFragment(PayloadFragment { seq, .. }) => Some(*seq),
+            Frame::Connect(_)
+            | Frame::Hello(_)
+            | Frame::ConnectV2(_)
+            | Frame::WindowUpdate(_)
+            | Frame::ConnectSecure(_)
+            | Frame::Ack(_)
+            | Frame::Private(_) => None,
+        }
+    }
 }
 
 /// # Format
@@ -27,9 +94,31 @@ pub enum Frame {
 /// ```
 ///
 /// - Data size field length: `u16`
+///
+/// A retransmitted payload takes frame type `15` instead, with the same fields, so the receiver can tell it apart from the original.
+///
+/// A payload with no data is a keepalive that still occupies a sequence and is acked like any payload, unlike `Ping`, which is sequenced per socket and only measures RTT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Payload {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
     pub seq: Seq16,
     pub data: Vec<u8>,
+    /// The payload was sent before, so its ack should not be taken as an RTT sample
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retransmit: bool,
+}
+
+impl Payload {
+    /// A keepalive payload with no data
+    #[must_use]
+    pub fn empty(seq: Seq16) -> Self {
+        Self {
+            seq,
+            data: Vec::new(),
+            retransmit: false,
+        }
+    }
 }
 
 /// # Format
@@ -37,7 +126,10 @@ pub struct Payload {
 /// ```text
 /// ( 1, Seq )
 /// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PayloadAck {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
     pub seq: Seq16,
 }
 
@@ -46,7 +138,10 @@ pub struct PayloadAck {
 /// ```text
 /// ( 2, Seq )
 /// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ping {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
     pub seq: Seq16,
 }
 
@@ -55,7 +150,10 @@ pub struct Ping {
 /// ```text
 /// ( 3, Seq )
 /// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PingAck {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
     pub seq: Seq16,
 }
 
@@ -64,6 +162,784 @@ pub struct PingAck {
 /// ```text
 /// ( 4, Connection ID )
 /// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connect {
-    pub connection_id: u32,
+    pub connection_id: ConnectionId,
+}
+
+/// A `Connect` that also negotiates the receive window.
+///
+/// # Format
+///
+/// ```text
+/// ( 10, Connection ID, Initial seq, Receive window )
+/// ```
+///
+/// - Receive window field length: `u16`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectV2 {
+    pub connection_id: ConnectionId,
+    /// The first sequence the sender will use
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
+    pub initial_seq: Seq16,
+    /// The capacity of the receive queue of the sender of this frame
+    pub recv_window: u16,
+}
+
+/// A `Connect` that carries a nonce, so a replay can be told apart from a new connection attempt; see `ConnectTracker`.
+///
+/// # Format
+///
+/// ```text
+/// ( 6, Connection ID, Nonce )
+/// ```
+///
+/// - Nonce field length: `u64`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectSecure {
+    pub connection_id: ConnectionId,
+    /// Random for each connection attempt
+    pub nonce: u64,
+}
+
+/// - Field length: `u32`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionId(u32);
+
+impl ConnectionId {
+    #[must_use]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for ConnectionId {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+/// Exchanged before `Connect` to negotiate features.
+///
+/// # Format
+///
+/// ```text
+/// ( 5, Version, Capabilities )
+/// ```
+///
+/// - Version field length: `u8`
+/// - Capabilities field length: `u32`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hello {
+    pub version: u8,
+    /// A bitfield of supported frame types and features
+    pub capabilities: u32,
+}
+
+impl Hello {
+    /// Return `true` if every bit of `capability` is set.
+    #[must_use]
+    pub fn has_capability(&self, capability: u32) -> bool {
+        self.capabilities & capability == capability
+    }
+}
+
+/// Explicitly declares a payload lost.
+///
+/// # Format
+///
+/// ```text
+/// ( 8, Seq )
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nack {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
+    pub seq: Seq16,
+}
+
+/// A payload ack that echoes the congestion experienced (CE) marks seen by the receiver.
+///
+/// # Format
+///
+/// ```text
+/// ( 9, Seq, CE count )
+/// ```
+///
+/// - CE count field length: `u16`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PayloadAckEcn {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
+    pub seq: Seq16,
+    /// The number of CE-marked packets received since the last echo
+    pub ce_count: u16,
+}
+
+/// Advertises the room left in the receive queue of the sender of this frame.
+///
+/// # Format
+///
+/// ```text
+/// ( 11, Available )
+/// ```
+///
+/// - Available field length: `u16`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowUpdate {
+    /// The number of payloads the receive queue can still take
+    pub available: u16,
+}
+
+/// One fragment of a message too large for a single `Payload`.
+///
+/// The fragments of a message take consecutive sequences, so the message is identified by the sequence of its first fragment, `seq - frag_index`.
+///
+/// # Format
+///
+/// ```text
+/// ( 12, Seq, Fragment index, Last, Data size, Data )
+/// ```
+///
+/// - Fragment index field length: `u16`
+/// - Last field length: `u8`, which is `1` for the last fragment and `0` otherwise
+/// - Data size field length: `u16`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PayloadFragment {
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
+    pub seq: Seq16,
+    pub frag_index: u16,
+    /// No more fragments follow
+    pub last: bool,
+    pub data: Vec<u8>,
+}
+
+/// A payload ack that acks everything below a cumulative point plus the ranges received out of order beyond it.
+///
+/// # Format
+///
+/// ```text
+/// ( 13, Cumulative, Range count, Range start, Range end, ... )
+/// ```
+///
+/// - Range count field length: `u8`, so at most `255` ranges fit
+/// - Each range is inclusive and its start does not follow its end
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ack {
+    /// Every sequence strictly below is acked
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde"))]
+    pub cumulative: Seq16,
+    #[cfg_attr(feature = "serde", serde(with = "seq16_serde::ranges"))]
+    pub ranges: Vec<(Seq16, Seq16)>,
+}
+
+/// A frame of a type in `FrameRegistry::PRIVATE_USE`, left to the application to interpret.
+///
+/// Only `Frame::decode_with_registry` produces it, through a decoder registered for its type.
+///
+/// # Format
+///
+/// ```text
+/// ( Frame type, Body )
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateFrame {
+    pub frame_type: u8,
+    pub body: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn hello() {
+        let frame = Frame::Hello(Hello {
+            version: 1,
+            capabilities: 1 << 3,
+        });
+        let buf: Vec<u8> = (&frame).into();
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::Hello(hello) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(hello.version, 1);
+        assert!(hello.has_capability(1 << 3));
+        assert!(!hello.has_capability(1 << 2));
+    }
+
+    #[test]
+    fn hello_capabilities() {
+        let hello = Hello {
+            version: 0,
+            capabilities: 0b101,
+        };
+        assert!(hello.has_capability(0b001));
+        assert!(hello.has_capability(0b100));
+        assert!(hello.has_capability(0b101));
+        assert!(!hello.has_capability(0b010));
+        assert!(!hello.has_capability(0b111));
+    }
+
+    #[test]
+    fn nack() {
+        let frame = Frame::Nack(Nack {
+            seq: Seq16::new(42),
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![8, 0, 42]);
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::Nack(nack) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(nack.seq, Seq16::new(42));
+
+        let buf = [8, 0];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::try_from(&mut reader).err(),
+            Some(DecodeError::InvalidNack)
+        );
+    }
+
+    #[test]
+    fn retransmit_payload() {
+        let frame = Frame::Payload(Payload {
+            seq: Seq16::new(7),
+            data: vec![1, 2],
+            retransmit: true,
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![15, 0, 7, 0, 2, 1, 2]);
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(Frame::try_from(&mut reader), Ok(frame));
+
+        // The original is still sent as type `0`
+        let frame = Frame::Payload(Payload {
+            seq: Seq16::new(7),
+            data: vec![1, 2],
+            retransmit: false,
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf[0], 0);
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(Frame::try_from(&mut reader), Ok(frame));
+    }
+
+    #[test]
+    fn empty_payload() {
+        let frame = Frame::Payload(Payload::empty(Seq16::new(7)));
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![0, 0, 7, 0, 0]);
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::Payload(payload) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(payload.seq, Seq16::new(7));
+        assert!(payload.data.is_empty());
+    }
+
+    #[test]
+    fn payload_ack_ecn() {
+        let frame = Frame::PayloadAckEcn(PayloadAckEcn {
+            seq: Seq16::new(42),
+            ce_count: 3,
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![9, 0, 42, 0, 3]);
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::PayloadAckEcn(ack) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(ack.seq, Seq16::new(42));
+        assert_eq!(ack.ce_count, 3);
+
+        let buf = [9, 0, 42, 0];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::try_from(&mut reader).err(),
+            Some(DecodeError::InvalidPayloadAckEcn)
+        );
+    }
+
+    #[test]
+    fn decode_arbitrary_bytes() {
+        // xorshift32 for reproducible pseudo-random input
+        let mut state: u32 = 0x2545_f491;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        for _ in 0..10_000 {
+            let len = next() as usize % 32;
+            let mut buf = (0..len).map(|_| next() as u8).collect::<Vec<_>>();
+            // Favor known frame types
+            if let Some(frame_type) = buf.first_mut() {
+                *frame_type %= 12;
+            }
+
+            let mut reader = BufReader::new(&buf[..]);
+            if let Ok(frame) = Frame::decode(&mut reader) {
+                assert!(frame.encoded_len() <= buf.len());
+            }
+            let options = DecodeOptions {
+                max_payload_size: 8,
+            };
+            let mut reader = BufReader::new(&buf[..]);
+            let _ = Frame::decode_with(&mut reader, &options);
+        }
+
+        // A declared data size larger than the input
+        let buf = [0, 0, 0, 0xff, 0xff, 1, 2, 3];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode(&mut reader).err(),
+            Some(DecodeError::InvalidPayload)
+        );
+    }
+
+    #[test]
+    fn window_update() {
+        let frame = Frame::WindowUpdate(WindowUpdate { available: 300 });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![11, 1, 44]);
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::WindowUpdate(window_update) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(window_update.available, 300);
+
+        let buf = [11, 1];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::try_from(&mut reader).err(),
+            Some(DecodeError::InvalidWindowUpdate)
+        );
+    }
+
+    #[test]
+    fn payload_fragment() {
+        let frame = Frame::PayloadFragment(PayloadFragment {
+            seq: Seq16::new(42),
+            frag_index: 2,
+            last: true,
+            data: vec![1, 2, 3],
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![12, 0, 42, 0, 2, 1, 0, 3, 1, 2, 3]);
+        assert!(frame.roundtrip_ok());
+
+        // Neither the last nor a middle fragment
+        let buf = [12, 0, 42, 0, 2, 2, 0, 0];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::try_from(&mut reader).err(),
+            Some(DecodeError::InvalidPayloadFragment)
+        );
+
+        let options = DecodeOptions {
+            max_payload_size: 2,
+        };
+        let buf: Vec<u8> = (&frame).into();
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode_with(&mut reader, &options).err(),
+            Some(DecodeError::PayloadTooLarge)
+        );
+    }
+
+    #[test]
+    fn incomplete_or_malformed() {
+        // Need more data
+        let mut reader = BufReader::new(&[][..]);
+        assert_eq!(
+            Frame::decode(&mut reader).err(),
+            Some(DecodeError::UnexpectedEof)
+        );
+
+        // Malformed
+        for (buf, err) in [
+            (&[255, 0, 1][..], DecodeError::InvalidFrameType),
+            (&[2], DecodeError::InvalidPing),
+            (&[0, 0, 1, 0, 2, 9], DecodeError::InvalidPayload),
+        ] {
+            let mut reader = BufReader::new(buf);
+            assert_eq!(Frame::decode(&mut reader).err(), Some(err));
+        }
+
+        let err = std::io::Error::from(std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            DecodeError::from(err),
+            DecodeError::Io(std::io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn connect_secure() {
+        let frame = Frame::ConnectSecure(ConnectSecure {
+            connection_id: 7.into(),
+            nonce: 0x0102_0304_0506_0708,
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![6, 0, 0, 0, 7, 1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(Frame::try_from(&mut reader), Ok(frame));
+    }
+
+    #[test]
+    fn ack() {
+        let frame = Frame::Ack(Ack {
+            cumulative: Seq16::new(3),
+            ranges: vec![
+                (Seq16::new(5), Seq16::new(6)),
+                (Seq16::new(9), Seq16::new(9)),
+            ],
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![13, 0, 3, 2, 0, 5, 0, 6, 0, 9, 0, 9]);
+        assert_eq!(frame.encoded_len(), buf.len());
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(Frame::try_from(&mut reader), Ok(frame));
+
+        // The range ends before it starts
+        let buf = [13, 0, 3, 1, 0, 6, 0, 5];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(Frame::try_from(&mut reader), Err(DecodeError::InvalidAck));
+
+        // Missing a range
+        let buf = [13, 0, 3, 2, 0, 5, 0, 6];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(Frame::try_from(&mut reader), Err(DecodeError::InvalidAck));
+    }
+
+    #[test]
+    fn connect_v2() {
+        let frame = Frame::ConnectV2(ConnectV2 {
+            connection_id: 7.into(),
+            initial_seq: Seq16::new(1000),
+            recv_window: 64,
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![10, 0, 0, 0, 7, 0x03, 0xe8, 0, 64]);
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::ConnectV2(connect) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(connect.connection_id, 7.into());
+        assert_eq!(connect.initial_seq, Seq16::new(1000));
+        assert_eq!(connect.recv_window, 64);
+
+        // Initialize the receive queue from the negotiated fields
+        let mut queue =
+            crate::core::ReceiveQueue::with_start(connect.recv_window.into(), connect.initial_seq);
+        assert_eq!(
+            queue.receive(Seq16::new(1000)),
+            crate::core::ReceiveResult::AcceptDeliverable(1)
+        );
+
+        let buf = [10, 0, 0, 0, 7, 0x03, 0xe8, 0];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::try_from(&mut reader).err(),
+            Some(DecodeError::InvalidConnect)
+        );
+
+        // The old `Connect` is still decodable
+        let buf = [4, 0, 0, 0, 7];
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::Connect(connect) = Frame::try_from(&mut reader).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(connect.connection_id, 7.into());
+    }
+
+    #[test]
+    fn max_payload_size() {
+        let frame = Frame::Payload(Payload {
+            seq: Seq16::new(0),
+            data: vec![0; 5],
+            retransmit: false,
+        });
+        let buf: Vec<u8> = (&frame).into();
+
+        let options = DecodeOptions {
+            max_payload_size: 4,
+        };
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode_with(&mut reader, &options).err(),
+            Some(DecodeError::PayloadTooLarge)
+        );
+
+        let options = DecodeOptions {
+            max_payload_size: 5,
+        };
+        let mut reader = BufReader::new(&buf[..]);
+        let Frame::Payload(payload) = Frame::decode_with(&mut reader, &options).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(payload.data.len(), 5);
+
+        let mut reader = BufReader::new(&buf[..]);
+        assert!(Frame::decode(&mut reader).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_roundtrip() {
+        let frame = Frame::Payload(Payload {
+            seq: Seq16::new(3),
+            data: vec![1, 2, 3],
+            retransmit: false,
+        });
+        let json = serde_json::to_string(&frame).unwrap();
+        let Frame::Payload(payload) = serde_json::from_str::<Frame>(&json).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(payload.seq, Seq16::new(3));
+        assert_eq!(payload.data, vec![1, 2, 3]);
+
+        let frame = Frame::Connect(Connect {
+            connection_id: 7.into(),
+        });
+        let json = serde_json::to_string(&frame).unwrap();
+        let Frame::Connect(connect) = serde_json::from_str::<Frame>(&json).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(connect.connection_id, 7.into());
+    }
+
+    #[test]
+    fn roundtrip_ok() {
+        let frames = vec![
+            Frame::Payload(Payload {
+                seq: Seq16::new(0),
+                data: vec![1, 2, 3],
+                retransmit: false,
+            }),
+            Frame::PayloadAck(PayloadAck { seq: Seq16::new(1) }),
+            Frame::Ping(Ping { seq: Seq16::new(2) }),
+            Frame::PingAck(PingAck { seq: Seq16::new(3) }),
+            Frame::Connect(Connect {
+                connection_id: 4.into(),
+            }),
+            Frame::Hello(Hello {
+                version: 5,
+                capabilities: 6,
+            }),
+            Frame::Nack(Nack { seq: Seq16::new(7) }),
+            Frame::PayloadAckEcn(PayloadAckEcn {
+                seq: Seq16::new(8),
+                ce_count: 9,
+            }),
+            Frame::ConnectV2(ConnectV2 {
+                connection_id: 10.into(),
+                initial_seq: Seq16::new(11),
+                recv_window: 12,
+            }),
+            Frame::WindowUpdate(WindowUpdate { available: 13 }),
+            Frame::PayloadFragment(PayloadFragment {
+                seq: Seq16::new(14),
+                frag_index: 15,
+                last: true,
+                data: vec![16, 17],
+            }),
+            Frame::ConnectSecure(ConnectSecure {
+                connection_id: 18.into(),
+                nonce: 19,
+            }),
+            Frame::Ack(Ack {
+                cumulative: Seq16::new(20),
+                ranges: vec![(Seq16::new(22), Seq16::new(23))],
+            }),
+            Frame::Payload(Payload {
+                seq: Seq16::new(24),
+                data: vec![25],
+                retransmit: true,
+            }),
+        ];
+        for frame in &frames {
+            assert!(frame.roundtrip_ok());
+            assert_eq!(frame.clone(), *frame);
+        }
+        assert_ne!(frames[0], frames[1]);
+
+        // The data size field cannot hold the data
+        let frame = Frame::Payload(Payload {
+            seq: Seq16::new(0),
+            data: vec![0; u16::MAX as usize + 1],
+            retransmit: false,
+        });
+        assert!(!frame.roundtrip_ok());
+    }
+
+    #[test]
+    fn encoded_len() {
+        let frames = vec![
+            Frame::Payload(Payload {
+                seq: Seq16::new(0),
+                data: vec![1, 2, 3],
+                retransmit: false,
+            }),
+            Frame::PayloadAck(PayloadAck { seq: Seq16::new(1) }),
+            Frame::Ping(Ping { seq: Seq16::new(2) }),
+            Frame::PingAck(PingAck { seq: Seq16::new(3) }),
+            Frame::Connect(Connect {
+                connection_id: 4.into(),
+            }),
+            Frame::Hello(Hello {
+                version: 5,
+                capabilities: 6,
+            }),
+            Frame::Nack(Nack { seq: Seq16::new(7) }),
+            Frame::PayloadAckEcn(PayloadAckEcn {
+                seq: Seq16::new(8),
+                ce_count: 9,
+            }),
+            Frame::ConnectV2(ConnectV2 {
+                connection_id: 10.into(),
+                initial_seq: Seq16::new(11),
+                recv_window: 12,
+            }),
+            Frame::WindowUpdate(WindowUpdate { available: 13 }),
+            Frame::PayloadFragment(PayloadFragment {
+                seq: Seq16::new(14),
+                frag_index: 15,
+                last: false,
+                data: vec![16, 17],
+            }),
+            Frame::ConnectSecure(ConnectSecure {
+                connection_id: 18.into(),
+                nonce: 19,
+            }),
+            Frame::Ack(Ack {
+                cumulative: Seq16::new(20),
+                ranges: vec![(Seq16::new(22), Seq16::new(23))],
+            }),
+            Frame::Payload(Payload {
+                seq: Seq16::new(24),
+                data: vec![25],
+                retransmit: true,
+            }),
+        ];
+        for frame in frames {
+            let buf: Vec<u8> = (&frame).into();
+            assert_eq!(frame.encoded_len(), buf.len());
+            assert_eq!(frame.frame_type(), buf[0]);
+        }
+    }
+
+    #[test]
+    fn classifiers() {
+        let seq = Seq16::new(7);
+        // (frame, is_ack, is_data, seq)
+        let cases = vec![
+            (Frame::Payload(Payload::empty(seq)), false, true, Some(seq)),
+            (
+                Frame::PayloadAck(PayloadAck { seq }),
+                true,
+                false,
+                Some(seq),
+            ),
+            (Frame::Ping(Ping { seq }), false, false, Some(seq)),
+            (Frame::PingAck(PingAck { seq }), true, false, Some(seq)),
+            (
+                Frame::Connect(Connect {
+                    connection_id: 1.into(),
+                }),
+                false,
+                false,
+                None,
+            ),
+            (
+                Frame::Hello(Hello {
+                    version: 1,
+                    capabilities: 0,
+                }),
+                false,
+                false,
+                None,
+            ),
+            (Frame::Nack(Nack { seq }), false, false, Some(seq)),
+            (
+                Frame::PayloadAckEcn(PayloadAckEcn { seq, ce_count: 1 }),
+                true,
+                false,
+                Some(seq),
+            ),
+            (
+                Frame::ConnectV2(ConnectV2 {
+                    connection_id: 1.into(),
+                    initial_seq: seq,
+                    recv_window: 1,
+                }),
+                false,
+                false,
+                None,
+            ),
+            (
+                Frame::WindowUpdate(WindowUpdate { available: 1 }),
+                false,
+                false,
+                None,
+            ),
+            (
+                Frame::PayloadFragment(PayloadFragment {
+                    seq,
+                    frag_index: 0,
+                    last: true,
+                    data: vec![1],
+                }),
+                false,
+                true,
+                Some(seq),
+            ),
+            (
+                Frame::ConnectSecure(ConnectSecure {
+                    connection_id: 1.into(),
+                    nonce: 1,
+                }),
+                false,
+                false,
+                None,
+            ),
+            (
+                Frame::Ack(Ack {
+                    cumulative: seq,
+                    ranges: Vec::new(),
+                }),
+                true,
+                false,
+                None,
+            ),
+            (
+                Frame::Private(PrivateFrame {
+                    frame_type: 240,
+                    body: Vec::new(),
+                }),
+                false,
+                false,
+                None,
+            ),
+        ];
+        for (frame, is_ack, is_data, expected_seq) in cases {
+            assert_eq!(frame.is_ack(), is_ack, "{frame:?}");
+            assert_eq!(frame.is_data(), is_data, "{frame:?}");
+            assert_eq!(frame.seq(), expected_seq, "{frame:?}");
+        }
+    }
 }
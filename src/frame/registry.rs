@@ -0,0 +1,149 @@
+use std::{collections::HashMap, io::BufReader, ops::RangeInclusive};
+
+use byteorder::ReadBytesExt;
+use thiserror::Error;
+
+use super::{DecodeError, DecodeOptions, Frame};
+
+/// Decode the rest of a frame after its frame type.
+pub type FrameDecodeFn =
+    Box<dyn Fn(&mut BufReader<&[u8]>, &DecodeOptions) -> Result<Frame, DecodeError>>;
+
+/// Map frame types to their decoders.
+///
+/// The built-in frame types are registered on creation.
+pub struct FrameRegistry {
+    decoders: HashMap<u8, FrameDecodeFn>,
+}
+
+impl FrameRegistry {
+    /// The frame types open to `register`
+    pub const PRIVATE_USE: RangeInclusive<u8> = 240..=255;
+
+    const BUILT_IN: [u8; 14] = [0, 1, 2, 3, 4, 5, 6, 8, 9, 10, 11, 12, 13, 15];
+
+    #[must_use]
+    pub fn new() -> Self {
+        let mut decoders: HashMap<u8, FrameDecodeFn> = HashMap::new();
+        for frame_type in Self::BUILT_IN {
+            decoders.insert(
+                frame_type,
+                Box::new(move |value, options| Frame::decode_body(frame_type, value, options)),
+            );
+        }
+        Self { decoders }
+    }
+
+    /// Replace the decoder if `frame_type` is already registered.
+    pub fn register<F>(&mut self, frame_type: u8, decoder: F) -> Result<(), RegisterFrameError>
+    where
+        F: Fn(&mut BufReader<&[u8]>, &DecodeOptions) -> Result<Frame, DecodeError> + 'static,
+    {
+        if !Self::PRIVATE_USE.contains(&frame_type) {
+            return Err(RegisterFrameError::NotPrivateUse(frame_type));
+        }
+        self.decoders.insert(frame_type, Box::new(decoder));
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn contains(&self, frame_type: u8) -> bool {
+        self.decoders.contains_key(&frame_type)
+    }
+}
+
+impl Default for FrameRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frame {
+    /// Decode with the decoder registered for the frame type.
+    pub fn decode_with_registry(
+        value: &mut BufReader<&[u8]>,
+        options: &DecodeOptions,
+        registry: &FrameRegistry,
+    ) -> Result<Self, DecodeError> {
+        let frame_type = value.read_u8()?;
+        let Some(decoder) = registry.decoders.get(&frame_type) else {
+            return Err(DecodeError::InvalidFrameType);
+        };
+        decoder(value, options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum RegisterFrameError {
+    #[error("frame type {0} is not in the private use range")]
+    NotPrivateUse(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use seq::Seq16;
+
+    use super::*;
+    use crate::frame::{Ping, PrivateFrame};
+
+    #[test]
+    fn custom_type() {
+        let mut registry = FrameRegistry::new();
+        assert_eq!(
+            registry.register(7, |_, _| Err(DecodeError::InvalidFrameType)),
+            Err(RegisterFrameError::NotPrivateUse(7))
+        );
+
+        // A body of exactly 3 bytes
+        registry
+            .register(240, |value, _| {
+                let mut body = vec![0; 3];
+                value.read_exact(&mut body)?;
+                Ok(Frame::Private(PrivateFrame {
+                    frame_type: 240,
+                    body,
+                }))
+            })
+            .unwrap();
+        assert!(registry.contains(240));
+
+        let frame = Frame::Private(PrivateFrame {
+            frame_type: 240,
+            body: vec![1, 2, 3],
+        });
+        let buf: Vec<u8> = (&frame).into();
+        assert_eq!(buf, vec![240, 1, 2, 3]);
+        let options = DecodeOptions::default();
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode_with_registry(&mut reader, &options, &registry),
+            Ok(frame)
+        );
+
+        // Unknown without the registry
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode(&mut reader),
+            Err(DecodeError::InvalidFrameType)
+        );
+
+        // Built-in types are still decoded
+        let frame = Frame::Ping(Ping { seq: Seq16::new(1) });
+        let buf: Vec<u8> = (&frame).into();
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode_with_registry(&mut reader, &options, &registry),
+            Ok(frame)
+        );
+
+        // Unregistered private type
+        let buf = [241, 0];
+        let mut reader = BufReader::new(&buf[..]);
+        assert_eq!(
+            Frame::decode_with_registry(&mut reader, &options, &registry),
+            Err(DecodeError::InvalidFrameType)
+        );
+    }
+}
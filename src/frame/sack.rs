@@ -0,0 +1,67 @@
+use std::collections::BTreeSet;
+
+use seq::Seq16;
+
+/// Coalesce acked sequences into the minimal inclusive ranges.
+///
+/// A run crossing the end of the sequence space is split into two ranges at the wrap.
+#[must_use]
+pub fn coalesce_acks(seqs: &BTreeSet<Seq16>) -> Vec<(Seq16, Seq16)> {
+    let mut ranges: Vec<(Seq16, Seq16)> = Vec::new();
+    for seq in seqs {
+        if let Some((_, end)) = ranges.last_mut() {
+            if end.value() != u16::MAX && end.add(1) == *seq {
+                // Extend the current run
+                *end = *seq;
+                continue;
+            }
+        }
+
+        // Start a new run
+        ranges.push((*seq, *seq));
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(seqs: &[u16]) -> BTreeSet<Seq16> {
+        seqs.iter().map(|seq| Seq16::new(*seq)).collect()
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(coalesce_acks(&set(&[])), vec![]);
+    }
+
+    #[test]
+    fn scattered() {
+        assert_eq!(
+            coalesce_acks(&set(&[1, 3, 4, 5, 9])),
+            vec![
+                (Seq16::new(1), Seq16::new(1)),
+                (Seq16::new(3), Seq16::new(5)),
+                (Seq16::new(9), Seq16::new(9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_run() {
+        let seqs = (10..=100).collect::<Vec<_>>();
+        assert_eq!(
+            coalesce_acks(&set(&seqs)),
+            vec![(Seq16::new(10), Seq16::new(100))]
+        );
+    }
+
+    #[test]
+    fn wrap() {
+        let ranges = coalesce_acks(&set(&[65534, 65535, 0, 1]));
+        assert_eq!(ranges.len(), 2);
+        assert!(ranges.contains(&(Seq16::new(65534), Seq16::new(65535))));
+        assert!(ranges.contains(&(Seq16::new(0), Seq16::new(1))));
+    }
+}
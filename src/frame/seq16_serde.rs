@@ -0,0 +1,70 @@
+use seq::Seq16;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S>(seq: &Seq16, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u16(seq.value())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Seq16, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Seq16::new(u16::deserialize(deserializer)?))
+}
+
+/// For inclusive ranges of sequences
+pub mod ranges {
+    use seq::Seq16;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(ranges: &[(Seq16, Seq16)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let ranges = ranges
+            .iter()
+            .map(|(start, end)| (start.value(), end.value()))
+            .collect::<Vec<_>>();
+        ranges.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(Seq16, Seq16)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ranges = Vec::<(u16, u16)>::deserialize(deserializer)?;
+        Ok(ranges
+            .into_iter()
+            .map(|(start, end)| (Seq16::new(start), Seq16::new(end)))
+            .collect())
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use seq::Seq16;
+
+    use crate::frame::{Ack, Frame};
+
+    #[test]
+    fn json_roundtrip() {
+        let frame = Frame::Ack(Ack {
+            cumulative: Seq16::new(65535),
+            ranges: vec![
+                (Seq16::new(2), Seq16::new(4)),
+                (Seq16::new(7), Seq16::new(7)),
+            ],
+        });
+        let json = serde_json::to_string(&frame).unwrap();
+
+        // Sequences are plain numbers and ranges are pairs of them
+        assert_eq!(
+            json,
+            r#"{"Ack":{"cumulative":65535,"ranges":[[2,4],[7,7]]}}"#
+        );
+        assert_eq!(serde_json::from_str::<Frame>(&json).unwrap(), frame);
+    }
+}
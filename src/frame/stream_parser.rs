@@ -0,0 +1,220 @@
+use std::io::BufReader;
+
+use super::{DecodeError, DecodeOptions, Frame};
+
+/// Reassemble frames that are split across the reads of a byte stream.
+pub struct StreamParser {
+    /// Bytes received but not yet parsed into frames
+    buf: Vec<u8>,
+    options: DecodeOptions,
+}
+
+impl StreamParser {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_options(DecodeOptions::default())
+    }
+
+    #[must_use]
+    pub fn with_options(options: DecodeOptions) -> Self {
+        Self {
+            buf: Vec::new(),
+            options,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Return `Ok(None)` if a complete frame is not yet available.
+    ///
+    /// Bytes are consumed only for fully-parsed frames.
+    /// An oversized payload is rejected as soon as its header arrives.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, DecodeError> {
+        let Some(frame_len) = declared_frame_len(&self.buf, &self.options)? else {
+            return Ok(None);
+        };
+        if self.buf.len() < frame_len {
+            return Ok(None);
+        }
+
+        let mut reader = BufReader::new(&self.buf[..frame_len]);
+        let frame = Frame::decode_with(&mut reader, &self.options)?;
+        self.buf.drain(..frame_len);
+        Ok(Some(frame))
+    }
+
+    /// The number of bytes buffered but not yet parsed
+    #[must_use]
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+impl Default for StreamParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Peek the total length of the frame at the start of `buf` without consuming it.
+///
+/// Return `Ok(None)` if the header is not yet complete.
+fn declared_frame_len(buf: &[u8], options: &DecodeOptions) -> Result<Option<usize>, DecodeError> {
+    let Some(frame_type) = buf.first() else {
+        return Ok(None);
+    };
+    let body_len = match frame_type {
+        0 | 15 => {
+            // Seq and data size
+            let Some(data_size) = declared_data_size(buf, 1 + 2, options)? else {
+                return Ok(None);
+            };
+            2 + 2 + data_size
+        }
+        1..=3 => 2,
+        4 => 4,
+        5 => 1 + 4,
+        6 => 4 + 8,
+        8 => 2,
+        9 => 2 + 2,
+        10 => 4 + 2 + 2,
+        11 => 2,
+        12 => {
+            // Seq, fragment index, last, and data size
+            let Some(data_size) = declared_data_size(buf, 1 + 2 + 2 + 1, options)? else {
+                return Ok(None);
+            };
+            2 + 2 + 1 + 2 + data_size
+        }
+        13 => {
+            // Cumulative and range count
+            let Some(range_count) = buf.get(1 + 2) else {
+                return Ok(None);
+            };
+            2 + 1 + (2 + 2) * *range_count as usize
+        }
+        _ => return Err(DecodeError::InvalidFrameType),
+    };
+    Ok(Some(1 + body_len))
+}
+
+/// Peek the data size field at `at`.
+fn declared_data_size(
+    buf: &[u8],
+    at: usize,
+    options: &DecodeOptions,
+) -> Result<Option<usize>, DecodeError> {
+    let Some(data_size) = buf.get(at..at + 2) else {
+        return Ok(None);
+    };
+    let data_size = u16::from_be_bytes([data_size[0], data_size[1]]);
+    if data_size as usize > options.max_payload_size {
+        return Err(DecodeError::PayloadTooLarge);
+    }
+    Ok(Some(data_size as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use seq::Seq16;
+
+    use super::*;
+    use crate::frame::{Ack, Payload, Ping};
+
+    #[test]
+    fn split_frame() {
+        let frame = Frame::Payload(Payload {
+            seq: Seq16::new(1),
+            data: vec![1, 2, 3, 4],
+            retransmit: false,
+        });
+        let buf: Vec<u8> = (&frame).into();
+
+        let mut parser = StreamParser::new();
+        parser.push(&buf[..2]);
+        assert!(parser.next_frame().unwrap().is_none());
+        parser.push(&buf[2..6]);
+        assert!(parser.next_frame().unwrap().is_none());
+        assert_eq!(parser.buffered_len(), 6);
+        parser.push(&buf[6..]);
+        let Some(Frame::Payload(payload)) = parser.next_frame().unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(payload.seq, Seq16::new(1));
+        assert_eq!(payload.data, vec![1, 2, 3, 4]);
+        assert_eq!(parser.buffered_len(), 0);
+        assert!(parser.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn consecutive_frames() {
+        let mut buf = Vec::new();
+        for i in 0..2 {
+            let frame = Frame::Ping(Ping { seq: Seq16::new(i) });
+            buf.extend::<Vec<u8>>((&frame).into());
+        }
+
+        let mut parser = StreamParser::new();
+        parser.push(&buf[..4]);
+        let Some(Frame::Ping(ping)) = parser.next_frame().unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(ping.seq, Seq16::new(0));
+        assert!(parser.next_frame().unwrap().is_none());
+        parser.push(&buf[4..]);
+        let Some(Frame::Ping(ping)) = parser.next_frame().unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(ping.seq, Seq16::new(1));
+    }
+
+    #[test]
+    fn split_ack() {
+        let frame = Frame::Ack(Ack {
+            cumulative: Seq16::new(1),
+            ranges: vec![
+                (Seq16::new(3), Seq16::new(4)),
+                (Seq16::new(7), Seq16::new(7)),
+            ],
+        });
+        let buf: Vec<u8> = (&frame).into();
+
+        // The range count is not in yet
+        let mut parser = StreamParser::new();
+        parser.push(&buf[..3]);
+        assert!(parser.next_frame().unwrap().is_none());
+        parser.push(&buf[3..buf.len() - 1]);
+        assert!(parser.next_frame().unwrap().is_none());
+        parser.push(&buf[buf.len() - 1..]);
+        assert_eq!(parser.next_frame(), Ok(Some(frame)));
+    }
+
+    #[test]
+    fn oversized_payload() {
+        let options = DecodeOptions {
+            max_payload_size: 1024,
+        };
+        let mut parser = StreamParser::with_options(options);
+
+        // The header claims 64 KiB but only a few bytes have arrived
+        parser.push(&[0, 0, 1, 0xff]);
+        assert!(parser.next_frame().unwrap().is_none());
+        parser.push(&[0xff, 1, 2]);
+        assert_eq!(
+            parser.next_frame().err(),
+            Some(DecodeError::PayloadTooLarge)
+        );
+    }
+
+    #[test]
+    fn invalid_frame_type() {
+        let mut parser = StreamParser::new();
+        parser.push(&[255]);
+        assert_eq!(
+            parser.next_frame().err(),
+            Some(DecodeError::InvalidFrameType)
+        );
+    }
+}
@@ -1,2 +1,12 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("either the `std` or the `alloc` feature must be enabled");
+
+extern crate alloc;
+
 pub mod core;
+#[cfg(feature = "std")]
 pub mod frame;
+#[cfg(feature = "testing")]
+pub mod testing;
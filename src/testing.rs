@@ -0,0 +1,279 @@
+//! Drive a `Sender` and a `ReceiveQueue` over simulated lossy paths.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    os::fd::RawFd,
+    time,
+};
+
+use seq::Seq16;
+
+use crate::{
+    core::{
+        sender::{
+            AckSpace, Clock, MockClock, MockInstant, SendConfig, SendFrame, Sender, SplitMix64,
+        },
+        ReceiveQueue, ReceiveResult,
+    },
+    frame::Frame,
+};
+
+/// The conditions of a simulated path, applied to each direction on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathConfig {
+    /// The probability that a packet is dropped
+    pub loss: f64,
+    /// The one-way delay
+    pub delay: time::Duration,
+    /// The extra one-way delay, uniformly drawn from `[0, jitter)` for each packet
+    pub jitter: time::Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SimReport {
+    /// Payloads accepted by the receive queue for the first time
+    pub delivered: usize,
+    /// Packets dropped by the paths
+    pub lost: usize,
+    /// Payloads retransmitted after a timeout
+    pub retransmitted: usize,
+}
+
+/// A reproducible network of paths between a `Sender` and a `ReceiveQueue`.
+pub struct NetworkSim {
+    sender: Sender<MockInstant>,
+    receive_queue: ReceiveQueue<MockInstant>,
+    paths: HashMap<RawFd, PathConfig>,
+    clock: MockClock,
+    rng: SplitMix64,
+    /// Packets on the wire keyed by their arrival time and a tie breaker
+    in_flight: BTreeMap<(MockInstant, u64), Packet>,
+    next_packet_id: u64,
+    report: SimReport,
+}
+
+impl NetworkSim {
+    /// The sender and the paths are both seeded with `seed`, overriding `config.rng_seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` is invalid.
+    #[must_use]
+    pub fn new(config: SendConfig, recv_window: usize, seed: u64) -> Self {
+        let config = SendConfig {
+            rng_seed: Some(seed),
+            ..config
+        };
+        Self {
+            sender: Sender::new(config),
+            receive_queue: ReceiveQueue::with_timestamp(recv_window, Seq16::new(0)),
+            paths: HashMap::new(),
+            clock: MockClock::new(),
+            rng: SplitMix64::new(seed),
+            in_flight: BTreeMap::new(),
+            next_packet_id: 0,
+            report: SimReport::default(),
+        }
+    }
+
+    pub fn add_path(&mut self, fd: RawFd, path: PathConfig) {
+        self.paths.insert(fd, path);
+        self.sender.add_fd(fd);
+    }
+
+    /// Advance the clock by `elapsed`, deliver the packets that have arrived by then, retransmit the timed out payloads, and send `payload_size` units of new payloads.
+    pub fn step(&mut self, elapsed: time::Duration, payload_size: usize) {
+        self.clock.advance(elapsed);
+        let now = self.clock.now();
+
+        // Deliver arrived packets
+        while let Some(entry) = self.in_flight.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let packet = entry.remove();
+            self.deliver(now, packet);
+        }
+
+        // Retransmit timed out payloads
+        let retx = self.sender.on_timeout_tick(now, |_| Some(Vec::new()));
+        for (fd, frame) in retx {
+            let Frame::Payload(payload) = frame else {
+                continue;
+            };
+            self.report.retransmitted += 1;
+            self.transmit(
+                now,
+                Packet::Payload {
+                    fd,
+                    seq: payload.seq,
+                },
+            );
+        }
+
+        // Send new payloads
+        let mut packets = self
+            .sender
+            .send(now, payload_size)
+            .into_iter()
+            .map(|frame| match frame {
+                SendFrame::Payload(frame) => Packet::Payload {
+                    fd: frame.fd,
+                    seq: frame.seq,
+                },
+                SendFrame::Ping(frame) => Packet::Ping {
+                    fd: frame.fd,
+                    seq: frame.seq,
+                },
+                SendFrame::Retransmit(frame) => Packet::Payload {
+                    fd: frame.fd,
+                    seq: frame.seq,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        // Draw from the RNG in a reproducible order
+        packets.sort_unstable();
+        for packet in packets {
+            self.transmit(now, packet);
+        }
+    }
+
+    #[must_use]
+    pub fn report(&self) -> SimReport {
+        self.report
+    }
+
+    #[must_use]
+    pub fn sender(&self) -> &Sender<MockInstant> {
+        &self.sender
+    }
+
+    #[must_use]
+    pub fn now(&self) -> MockInstant {
+        self.clock.now()
+    }
+
+    fn deliver(&mut self, now: MockInstant, packet: Packet) {
+        match packet {
+            Packet::Payload { fd, seq } => {
+                match self.receive_queue.receive(seq) {
+                    ReceiveResult::AcceptDeliverable(_) => {
+                        self.report.delivered += 1;
+                        while self.receive_queue.pop().is_some() {}
+                    }
+                    ReceiveResult::RejectTooOld | ReceiveResult::RejectDuplicate => {
+                        // The previous ack might have been lost
+                    }
+                    ReceiveResult::RejectBeyondWindow => return,
+                }
+                self.transmit(now, Packet::PayloadAck { fd, seq });
+            }
+            Packet::PayloadAck { fd, seq } => {
+                self.sender.ack(now, fd, seq, AckSpace::Payload);
+            }
+            Packet::Ping { fd, seq } => {
+                self.transmit(now, Packet::PingAck { fd, seq });
+            }
+            Packet::PingAck { fd, seq } => {
+                self.sender.ack(now, fd, seq, AckSpace::Ping);
+            }
+        }
+    }
+
+    fn transmit(&mut self, now: MockInstant, packet: Packet) {
+        let Some(path) = self.paths.get(&packet.fd()) else {
+            return;
+        };
+        if self.rng.next_f64() < path.loss {
+            self.report.lost += 1;
+            return;
+        }
+        let delay = path.delay + path.jitter.mul_f64(self.rng.next_f64());
+        self.in_flight
+            .insert((now + delay, self.next_packet_id), packet);
+        self.next_packet_id += 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Packet {
+    Payload { fd: RawFd, seq: Seq16 },
+    PayloadAck { fd: RawFd, seq: Seq16 },
+    Ping { fd: RawFd, seq: Seq16 },
+    PingAck { fd: RawFd, seq: Seq16 },
+}
+
+impl Packet {
+    fn fd(&self) -> RawFd {
+        match self {
+            Packet::Payload { fd, .. }
+            | Packet::PayloadAck { fd, .. }
+            | Packet::Ping { fd, .. }
+            | Packet::PingAck { fd, .. } => *fd,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossy_and_clean_paths() {
+        let config = SendConfig {
+            payload_queue_size: 256,
+            default_rto: time::Duration::from_millis(200),
+            ..SendConfig::default()
+        };
+        let mut sim = NetworkSim::new(config, 256, 42);
+
+        let lossy = 1;
+        let clean = 2;
+        sim.add_path(
+            lossy,
+            PathConfig {
+                loss: 0.3,
+                delay: time::Duration::from_millis(20),
+                jitter: time::Duration::from_millis(5),
+            },
+        );
+        sim.add_path(
+            clean,
+            PathConfig {
+                loss: 0.0,
+                delay: time::Duration::from_millis(20),
+                jitter: time::Duration::from_millis(5),
+            },
+        );
+
+        for _ in 0..2000 {
+            sim.step(time::Duration::from_millis(10), 10);
+        }
+
+        let report = sim.report();
+        let metrics = sim.sender().metrics();
+        assert!(report.lost > 0);
+
+        // Every payload is delivered but the ones still in flight
+        let in_flight = metrics.outstanding.values().sum::<usize>();
+        assert!(report.delivered + in_flight >= metrics.payloads_sent);
+
+        // Each timeout retransmits its payload once, and only losses time out
+        assert!(report.retransmitted > 0);
+        assert_eq!(report.retransmitted, metrics.rtos);
+        assert!(report.retransmitted <= report.lost);
+        let weights = sim.sender().weights();
+        let lossy_weight = weights.get(&lossy).copied().unwrap_or(0.0);
+        assert!(weights[&clean] > lossy_weight);
+
+        // Reproducible from the seed
+        let mut again = NetworkSim::new(config, 256, 42);
+        again.add_path(lossy, sim.paths[&lossy]);
+        again.add_path(clean, sim.paths[&clean]);
+        for _ in 0..2000 {
+            again.step(time::Duration::from_millis(10), 10);
+        }
+        assert_eq!(again.report(), report);
+    }
+}